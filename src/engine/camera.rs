@@ -1,16 +1,17 @@
-use vulkano::buffer::BufferContents;
-
 #[derive(Clone)]
 pub struct Camera {
     pub center: [f32; 2],
-    pub zoom: f32
+    pub zoom: f32,
+    // radians
+    pub rotation: f32
 }
 
 impl Camera {
     pub fn new() -> Camera {
         Camera {
             center: [0.0, 0.0],
-            zoom: 1.0
+            zoom: 1.0,
+            rotation: 0.0
         }
     }
 }
@@ -0,0 +1,68 @@
+use std::thread;
+
+use image::RgbaImage;
+
+use super::view::{Formula, View};
+
+// same bailout as the `cs` kernel
+const ESCAPE_RADIUS: f64 = 4.0;
+
+// renders a view on the cpu's threads in f64, for machines without a usable vulkan device.
+// only the grayscale iterations coloring, the others need the distance estimates and cycle
+// data of the gpu kernels. view.coloring and view.interior are ignored, see `Backend::Cpu`
+pub fn render(view: &View, size: [u32; 2]) -> RgbaImage {
+    let [width, height] = size;
+    let mut pixels = vec![0u8; width as usize * height as usize * 4];
+
+    let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let rows = ((height as usize + threads - 1) / threads).max(1);
+    let row_bytes = width as usize * 4;
+
+    thread::scope(|scope| {
+        for (band, chunk) in pixels.chunks_mut((rows * row_bytes).max(1)).enumerate() {
+            scope.spawn(move || {
+                for (i, pixel) in chunk.chunks_exact_mut(4).enumerate() {
+                    let index = band * rows * width as usize + i;
+                    let x = (index % width as usize) as f64;
+                    let y = (index / width as usize) as f64;
+
+                    let c = to_complex(view, [x, y], [width as f64, height as f64]);
+                    let value = escape_time(&view.formula, c, view.max_iters) as f64 / view.max_iters as f64;
+                    let value = (value * 255.0).round() as u8;
+
+                    pixel.copy_from_slice(&[value, value, value, 255]);
+                }
+            });
+        }
+    });
+
+    RgbaImage::from_raw(width, height, pixels).unwrap()
+}
+
+// same mapping as the `cs` kernel, 4 / zoom of the plane over the image height
+fn to_complex(view: &View, pixel: [f64; 2], dims: [f64; 2]) -> [f64; 2] {
+    let scale = 4.0 / (view.zoom * dims[1]);
+    let d = [(pixel[0] - dims[0] / 2.0) * scale, (pixel[1] - dims[1] / 2.0) * scale];
+
+    let (sin, cos) = view.rotation.sin_cos();
+
+    [
+        view.center[0] + cos * d[0] - sin * d[1],
+        view.center[1] + sin * d[0] + cos * d[1],
+    ]
+}
+
+// iterations before escaping, max_iters for points that never do
+fn escape_time(formula: &Formula, c: [f64; 2], max_iters: u32) -> u32 {
+    let mut z = c;
+
+    for i in 0..max_iters {
+        z = formula.step(z, c);
+
+        if (z[0] * z[0] + z[1] * z[1]).sqrt() > ESCAPE_RADIUS {
+            return i;
+        }
+    }
+
+    max_iters
+}
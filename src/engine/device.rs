@@ -0,0 +1,153 @@
+use std::sync::Arc;
+
+use vulkano::VulkanLibrary;
+use vulkano::device::DeviceExtensions;
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::instance::{Instance, InstanceCreateInfo};
+use vulkano_util::context::VulkanoConfig;
+
+// which physical device `--device` asked for, an index into the order `--list-devices`
+// prints them in or part of a name
+#[derive(Clone, Debug)]
+pub enum DeviceChoice {
+    Index(usize),
+    Name(String)
+}
+
+impl DeviceChoice {
+    pub fn parse(arg: &str) -> DeviceChoice {
+        match arg.parse() {
+            Ok(index) => DeviceChoice::Index(index),
+            Err(_) => DeviceChoice::Name(arg.to_string()),
+        }
+    }
+}
+
+// a physical device as every instance sees it, unlike its index or its name which two
+// identical cards share. the uuid tells those apart where the driver reports one
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceId {
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub uuid: Option<[u8; 16]>
+}
+
+impl DeviceId {
+    pub fn of(physical_device: &PhysicalDevice) -> DeviceId {
+        let properties = physical_device.properties();
+
+        DeviceId {
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            uuid: properties.device_uuid
+        }
+    }
+}
+
+// every physical device the vulkan library reports, software rasterizers like lavapipe
+// included
+fn physical_devices() -> Vec<Arc<PhysicalDevice>> {
+    let library = VulkanLibrary::new().expect("no local Vulkan library/DLL");
+
+    let instance = Instance::new(
+        library,
+        InstanceCreateInfo {
+            enumerate_portability: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+
+    instance.enumerate_physical_devices().unwrap().collect()
+}
+
+// prints what `--list-devices` shows, one block per device
+pub fn list_devices() {
+    for (index, physical_device) in physical_devices().iter().enumerate() {
+        let properties = physical_device.properties();
+
+        println!("{}: {} ({:?})", index, properties.device_name, properties.device_type);
+        println!("    api version {}, driver {}",
+            physical_device.api_version(),
+            properties.driver_name.as_deref().unwrap_or("unknown"));
+        println!("    f64 in shaders: {}", physical_device.supported_features().shader_float64);
+
+        for (family, queue_family) in physical_device.queue_family_properties().iter().enumerate() {
+            println!("    queue family {}: {} queues, {:?}, timestamps {}",
+                family,
+                queue_family.queue_count,
+                queue_family.queue_flags,
+                queue_family.timestamp_valid_bits.is_some());
+        }
+
+        println!("    max image size {}, max push constants {} bytes, max storage buffer {} bytes",
+            properties.max_image_dimension2_d,
+            properties.max_push_constants_size,
+            properties.max_storage_buffer_range);
+        println!("    compute: {} invocations per workgroup, {:?} workgroups, {} bytes shared",
+            properties.max_compute_work_group_invocations,
+            properties.max_compute_work_group_count,
+            properties.max_compute_shared_memory_size);
+    }
+}
+
+// the device the choice refers to, none if nothing matches. names match
+// case-insensitively on any part, the first match wins
+pub fn resolve(choice: &DeviceChoice) -> Option<DeviceId> {
+    let devices = physical_devices();
+
+    let physical_device = match choice {
+        DeviceChoice::Index(index) => devices.get(*index),
+        DeviceChoice::Name(name) => {
+            let name = name.to_lowercase();
+
+            devices
+                .iter()
+                .find(|physical_device| physical_device.properties().device_name.to_lowercase().contains(&name))
+        }
+    };
+
+    physical_device.map(|physical_device| DeviceId::of(physical_device))
+}
+
+// context config limited to the device, or vulkano_util's own pick without one
+pub fn config(device: Option<DeviceId>) -> VulkanoConfig {
+    let mut config = VulkanoConfig {
+        // the renderer says which device it got along with its queues
+        print_device_name: false,
+        ..Default::default()
+    };
+
+    if let Some(device) = device {
+        config.device_filter_fn = Arc::new(move |physical_device: &PhysicalDevice| {
+            physical_device.supported_extensions().khr_swapchain
+                && DeviceId::of(physical_device) == device
+        });
+    }
+
+    config
+}
+
+// like `config` for rendering without a window: no surface extensions on the instance and
+// no swapchain asked of the device, so headless machines and drivers that can't present
+// qualify
+pub fn offscreen_config(device: Option<DeviceId>) -> VulkanoConfig {
+    let mut config = VulkanoConfig {
+        instance_create_info: InstanceCreateInfo {
+            enumerate_portability: true,
+            ..Default::default()
+        },
+        device_extensions: DeviceExtensions::empty(),
+        device_filter_fn: Arc::new(|_: &PhysicalDevice| true),
+        print_device_name: false,
+        ..Default::default()
+    };
+
+    if let Some(device) = device {
+        config.device_filter_fn = Arc::new(move |physical_device: &PhysicalDevice| {
+            DeviceId::of(physical_device) == device
+        });
+    }
+
+    config
+}
@@ -1,49 +1,106 @@
-use std::ops::ControlFlow;
-use std::sync::Arc;
+use std::path::PathBuf;
 
-use vulkano::device::{DeviceExtensions, Features};
 use vulkano::swapchain::PresentMode;
-use vulkano_util::context::{VulkanoContext, VulkanoConfig};
-use vulkano_util::window::{self, VulkanoWindows, WindowDescriptor};
+use vulkano_util::context::VulkanoContext;
+use vulkano_util::window::{VulkanoWindows, WindowDescriptor, WindowResizeConstraints};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
 use winit::event_loop::EventLoop;
+use winit::window::Fullscreen;
 
 use super::camera::Camera;
+use super::device::{self, DeviceId};
+use super::orbit::Orbit;
+use super::view::{Formula, View};
 
-use super::renderer::render::Renderer;
+use super::renderer::render::{Renderer, RenderCamera};
 
 // default startup dimensions
 const WIDTH: u32 = 512;
 const HEIGHT: u32 = 512;
 
+// smallest the window can be resized to
+const MIN_WIDTH: f32 = 200.0;
+const MIN_HEIGHT: f32 = 200.0;
+
+// hi res screenshots are rendered at this multiple of the window size
+const SCREENSHOT_SCALE: u32 = 4;
+
 // default camera stuff
 const PAN_SPEED: f32 = 0.25;
 const ZOOM_SPEED: f32 = 0.3;
+// radians per key press or scroll line
+const ROTATE_SPEED: f32 = 0.05;
 // gengine impact REAL
 
 pub struct Engine {
     camera: Camera,
 
-    pub renderer: Renderer
+    pub renderer: Renderer,
+
+    // where the window was before going fullscreen, restored when leaving it
+    windowed_geometry: Option<WindowGeometry>,
+
+    // what the last presented frame was drawn from, none until one is
+    presented: Option<FrameInputs>,
+    // something `FrameInputs` doesn't cover changed since, e.g. a screenshot or the orbit
+    dirty: bool
+}
+
+// the state a frame on screen follows from. a frame is only drawn when these differ from the
+// last presented one or something marks the engine dirty
+#[derive(PartialEq)]
+struct FrameInputs {
+    camera: RenderCamera,
+    window_size: [f32; 2],
+    auto_iters: bool,
+
+    show_hud: bool,
+    show_julia: bool,
+    show_minimap: bool,
+    // only while the hud or the julia inset follows it
+    cursor: Option<[f32; 2]>
+}
+
+struct WindowGeometry {
+    position: Option<PhysicalPosition<i32>>,
+    size: PhysicalSize<u32>
 }
 
 impl Engine {
-    pub fn new() -> (Engine, EventLoop<()>) {
-        let context = VulkanoContext::new(VulkanoConfig::default());
+    // on the physical device, see `device::resolve`, or whichever one vulkano_util
+    // prefers without one. the tile cache spills to cache_dir once its memory is full, and
+    // forgets the oldest tiles without one
+    pub fn new(device: Option<DeviceId>, cache_dir: Option<PathBuf>) -> (Engine, EventLoop<()>) {
+        let event_loop = EventLoop::new();
+        let engine = Engine::with_event_loop(&event_loop, device, cache_dir);
+
+        (engine, event_loop)
+    }
+
+    // opens the window on an event loop the caller runs, for tools that embed the renderer
+    // next to windows of their own
+    pub fn with_event_loop(event_loop: &EventLoop<()>, device: Option<DeviceId>, cache_dir: Option<PathBuf>) -> Engine {
+        let context = VulkanoContext::new(device::config(device));
 
         let device = context.device();
 
         // window init stuff --
-        let event_loop = EventLoop::new();
         let mut windows = VulkanoWindows::default();
         
         let _window_id = windows.create_window(
-            &event_loop,
+            event_loop,
             &context,
             &WindowDescriptor {
                 width: WIDTH as f32,
                 height: HEIGHT as f32,
                 title: "Mandelbrowser".to_string(),
                 present_mode: PresentMode::Fifo,
+                resizable: true,
+                resize_constraints: WindowResizeConstraints {
+                    min_width: MIN_WIDTH,
+                    min_height: MIN_HEIGHT,
+                    ..Default::default()
+                },
                 ..Default::default()
             },
             |_| {},
@@ -54,61 +111,246 @@ impl Engine {
         // renderer initialization
         let camera = Camera::new();
 
-        let renderer = Renderer::new(windows, device.clone(), context, camera.clone());
+        let renderer = Renderer::new(windows, device.clone(), context, camera.clone(), cache_dir);
 
-        (Engine {
+        Engine {
             camera,
 
-            renderer
-        },
-        event_loop)
+            renderer,
+
+            windowed_geometry: None,
+
+            presented: None,
+            dirty: true
+        }
     }
 
+    // the view on screen, see `View`
+    pub fn view(&self) -> View {
+        View::from_camera(&self.render_camera(), self.renderer.formula(), self.renderer.window_size())
+    }
+
+    // the camera as the renderer gets it, see `Renderer::update_view`
+    fn render_camera(&self) -> RenderCamera {
+        let mut camera = RenderCamera::from(self.camera.clone());
+        camera.max_iters = self.renderer.iterations.limit(self.camera.zoom);
+        camera.coloring = self.renderer.coloring;
+        camera.interior = self.renderer.interior;
+
+        camera
+    }
+
+    fn frame_inputs(&self) -> FrameInputs {
+        let renderer = &self.renderer;
+
+        FrameInputs {
+            camera: self.render_camera(),
+            window_size: renderer.window_size(),
+            auto_iters: renderer.iterations.auto,
+
+            show_hud: renderer.show_hud,
+            show_julia: renderer.show_julia,
+            show_minimap: renderer.show_minimap,
+            cursor: if renderer.show_hud || renderer.show_julia { renderer.get_cursor() } else { None }
+        }
+    }
+
+    // moves the window to a view, its limit replaces the automatic one
+    pub fn set_view(&mut self, view: &View) {
+        let camera = view.camera(self.renderer.window_size());
+
+        self.camera.center = camera.translation;
+        self.camera.zoom = camera.zoom;
+        self.camera.rotation = camera.rotation;
+
+        self.renderer.iterations.set(view.max_iters);
+        self.renderer.coloring = view.coloring;
+        self.renderer.interior = view.interior;
+
+        if view.formula != *self.renderer.formula() {
+            if let Err(e) = self.renderer.set_formula(view.formula.clone()) {
+                eprintln!("{}", e);
+            }
+        }
+
+        self.dirty = true;
+    }
+
+    // iterates another formula, see `Formula`. the error is the shader compiler's
+    pub fn set_formula(&mut self, formula: Formula) -> Result<(), String> {
+        self.renderer.set_formula(formula)?;
+        self.dirty = true;
+
+        Ok(())
+    }
+
+    // presents a new frame if anything changed, otherwise the last one stays up and
+    // the gpu idles. the renderer itself skips compute when the view is the same
     pub fn render(&mut self) {
+        let inputs = self.frame_inputs();
+
+        if !self.dirty && self.presented.as_ref() == Some(&inputs) {
+            return;
+        }
+
         self.renderer.update_view(self.camera.clone());
 
-        self.renderer.render();
+        let presented = self.renderer.render();
+
+        self.presented = if presented { Some(inputs) } else { None };
+        self.dirty = self.renderer.is_refining();
+    }
+
+    // marks the frame as out of date, the next `render` draws it
+    pub fn request_redraw(&mut self) {
+        self.dirty = true;
     }
 
     pub fn camera_up(&mut self) {
         // let d = self.renderer.get_delta();
 
-        self.camera.center[1] -= PAN_SPEED / self.camera.zoom as f32;
+        self.pan([0.0, -PAN_SPEED]);
     }
 
     pub fn camera_down(&mut self) {
         // let d = self.renderer.get_delta();
 
-        self.camera.center[1] += PAN_SPEED / self.camera.zoom as f32;
+        self.pan([0.0, PAN_SPEED]);
     }
 
     pub fn camera_right(&mut self) {
         // let d = self.renderer.get_delta();
 
-        self.camera.center[0] += PAN_SPEED / self.camera.zoom as f32;
+        self.pan([PAN_SPEED, 0.0]);
     }
 
     pub fn camera_left(&mut self) {
         // let d = self.renderer.get_delta();
 
-        self.camera.center[0] -= PAN_SPEED / self.camera.zoom as f32;
+        self.pan([-PAN_SPEED, 0.0]);
+    }
+
+    // moves along the screen axes, which are turned by the view rotation. steps are
+    // snapped to whole pixels so the renderer can keep the pixels that stay on screen
+    fn pan(&mut self, step: [f32; 2]) {
+        let size = self.renderer.window_size();
+
+        let mut step = [step[0] / self.camera.zoom, step[1] / self.camera.zoom];
+
+        if size[1] > 0.0 {
+            // complex size of one pixel, the same along both axes
+            let pixel = 4.0 / (self.camera.zoom * size[1]);
+
+            step = [(step[0] / pixel).round() * pixel, (step[1] / pixel).round() * pixel];
+        }
+
+        let (sin, cos) = self.camera.rotation.sin_cos();
+
+        self.camera.center[0] += cos * step[0] - sin * step[1];
+        self.camera.center[1] += sin * step[0] + cos * step[1];
     }
 
+    // zooms around the middle of the window
     pub fn zoom(&mut self, delta: f32) {
+        let size = self.renderer.window_size();
 
-        self.camera.zoom += (delta * ZOOM_SPEED as f32) * 1.0f32.max(0.25 * self.camera.zoom);
-        self.camera.zoom = self.camera.zoom.max(1.0);
+        self.zoom_at([size[0] / 2.0, size[1] / 2.0], delta);
     }
 
-    pub fn resolution_up(&mut self) {
-        self.renderer.camera.max_iters += 10;
+    // zooms around the cursor, or the middle if it isn't in the window
+    pub fn zoom_at_cursor(&mut self, delta: f32) {
+        let pixel = self.anchor_pixel();
+
+        self.zoom_at(pixel, delta);
+    }
+
+    fn zoom_at(&mut self, pixel: [f32; 2], delta: f32) {
+        self.anchored(pixel, |camera| {
+            camera.zoom += (delta * ZOOM_SPEED as f32) * 1.0f32.max(0.25 * camera.zoom);
+            camera.zoom = camera.zoom.max(1.0);
+        });
+    }
 
+    // turns the view around the cursor, or the middle if it isn't in the window
+    pub fn rotate(&mut self, delta: f32) {
+        let pixel = self.anchor_pixel();
+
+        self.anchored(pixel, |camera| {
+            camera.rotation = (camera.rotation + delta * ROTATE_SPEED) % std::f32::consts::TAU;
+        });
+    }
+
+    fn anchor_pixel(&self) -> [f32; 2] {
+        let size = self.renderer.window_size();
+
+        self.renderer.get_cursor().unwrap_or([size[0] / 2.0, size[1] / 2.0])
+    }
+
+    // applies a camera change while keeping the complex point under the pixel in place
+    fn anchored(&mut self, pixel: [f32; 2], change: impl FnOnce(&mut Camera)) {
+        let size = self.renderer.window_size();
+
+        if size[0] == 0.0 || size[1] == 0.0 {
+            change(&mut self.camera);
+            return;
+        }
+
+        let before = RenderCamera::from(self.camera.clone()).to_complex(pixel, size);
+        change(&mut self.camera);
+        let after = RenderCamera::from(self.camera.clone()).to_complex(pixel, size);
+
+        self.camera.center[0] += before[0] - after[0];
+        self.camera.center[1] += before[1] - after[1];
+    }
+
+    pub fn resolution_up(&mut self) {
+        self.renderer.iterations.step(true);
     }
 
     pub fn resolution_down(&mut self) {
-        self.renderer.camera.max_iters -= 10;
+        self.renderer.iterations.step(false);
+    }
+
+    pub fn cycle_coloring(&mut self) {
+        self.renderer.coloring = self.renderer.coloring.next();
+    }
+
+    pub fn cycle_interior(&mut self) {
+        self.renderer.interior = self.renderer.interior.next();
+    }
+
+    // progressive tiles or mariani-silver subdivision, the latter only for the mandelbrot set
+    pub fn toggle_subdivide(&mut self) {
+        self.renderer.toggle_subdivide();
+        self.dirty = true;
+    }
+
+    // compiles the shaders from glsl files in dir instead, see `--shaders`
+    pub fn watch_shaders(&mut self, dir: PathBuf) {
+        self.renderer.watch_shaders(dir);
+        self.dirty = true;
+    }
 
-        self.renderer.camera.max_iters = self.renderer.camera.max_iters.max(10);
+    pub fn watches_shaders(&self) -> bool {
+        self.renderer.watches_shaders()
+    }
+
+    // picks up edited shader files, a new frame shows the result or the error
+    pub fn reload_shaders(&mut self) {
+        if self.renderer.reload_shaders() {
+            self.dirty = true;
+        }
+    }
+
+    // progressive tiles or views composed from the tile cache
+    pub fn toggle_cache(&mut self) {
+        self.renderer.toggle_cache();
+        self.dirty = true;
+    }
+
+    // automatic iteration limit on or off, E/Q offset it while it's on
+    pub fn toggle_auto_iters(&mut self) {
+        self.renderer.iterations.toggle_auto(self.camera.zoom);
     }
 
     pub fn get_zoom(&self) -> f32 {
@@ -117,6 +359,117 @@ impl Engine {
 
     pub fn resize(&mut self) {
         self.renderer.resize();
+        self.dirty = true;
+    }
+
+    pub fn cursor_moved(&mut self, position: [f32; 2]) {
+        self.renderer.update_cursor(Some(position));
+    }
+
+    pub fn cursor_left(&mut self) {
+        self.renderer.update_cursor(None);
+    }
+
+    // clicking the minimap jumps there, anywhere else computes the orbit of the
+    // point under the cursor and shows it as an overlay
+    pub fn select_point(&mut self) {
+        let cursor = match self.renderer.get_cursor() {
+            Some(cursor) => cursor,
+            None => return,
+        };
+
+        if let Some(target) = self.renderer.minimap_point(cursor) {
+            self.center_on(target);
+            return;
+        }
+
+        let c = self.renderer.camera.to_complex(cursor, self.renderer.window_size());
+
+        self.renderer.orbit = Some(Orbit::compute([c[0] as f64, c[1] as f64], self.renderer.camera.max_iters, self.renderer.formula()));
+        self.dirty = true;
+    }
+
+    // moves the camera so the point ends up in the middle of the window
+    pub fn center_on(&mut self, point: [f32; 2]) {
+        let size = self.renderer.window_size();
+        let view = RenderCamera::from(self.camera.clone());
+        let middle = view.to_complex([size[0] / 2.0, size[1] / 2.0], size);
+
+        self.camera.center[0] += point[0] - middle[0];
+        self.camera.center[1] += point[1] - middle[1];
+    }
+
+    pub fn screenshot(&mut self, hi_res: bool) {
+        let scale = if hi_res { SCREENSHOT_SCALE } else { 1 };
+
+        self.renderer.request_screenshot(scale);
+        self.dirty = true;
+    }
+
+    pub fn toggle_minimap(&mut self) {
+        self.renderer.show_minimap = !self.renderer.show_minimap;
+    }
+
+    pub fn clear_orbit(&mut self) {
+        if self.renderer.orbit.take().is_some() {
+            self.dirty = true;
+        }
+    }
+
+    pub fn toggle_hud(&mut self) {
+        self.renderer.show_hud = !self.renderer.show_hud;
+    }
+
+    pub fn toggle_julia(&mut self) {
+        self.renderer.show_julia = !self.renderer.show_julia;
+    }
+
+    // switches between windowed and fullscreen. exclusive takes over the display with its
+    // best video mode, otherwise it's a borderless window covering the current monitor
+    pub fn toggle_fullscreen(&mut self, exclusive: bool) {
+        if self.windowed_geometry.is_some() {
+            self.exit_fullscreen();
+            return;
+        }
+
+        let window = self.renderer.window();
+
+        self.windowed_geometry = Some(WindowGeometry {
+            position: window.outer_position().ok(),
+            size: window.inner_size()
+        });
+
+        let monitor = window.current_monitor();
+
+        let video_mode = match (exclusive, &monitor) {
+            (true, Some(monitor)) => monitor
+                .video_modes()
+                .max_by_key(|mode| (mode.size().width * mode.size().height, mode.refresh_rate_millihertz())),
+            _ => None,
+        };
+
+        let fullscreen = match video_mode {
+            Some(mode) => Fullscreen::Exclusive(mode),
+            None => Fullscreen::Borderless(monitor),
+        };
+
+        window.set_fullscreen(Some(fullscreen));
+    }
+
+    pub fn exit_fullscreen(&mut self) {
+        let geometry = match self.windowed_geometry.take() {
+            Some(geometry) => geometry,
+            None => return,
+        };
+
+        let window = self.renderer.window();
+
+        window.set_fullscreen(None);
+        window.set_inner_size(geometry.size);
+
+        if let Some(position) = geometry.position {
+            window.set_outer_position(position);
+        }
     }
 
     pub fn reset_camera(&mut self) {
@@ -0,0 +1,826 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+// a user formula for the iteration, z -> f(z, c) over complex numbers, e.g. `z^3 + c*sin(z)`
+// or `(|re z| + i|im z|)^2 + c`. it runs on the cpu through `step` and on the gpu as glsl
+// spliced into the kernels, see `specialize`
+//
+// z, c, i and pi are the names, numbers are real. + - * / and ^ work as usual, a product
+// can also be written side by side like `2z` or `i|im z|`. |x| is the modulus, the
+// functions are the ones in FUNCTIONS and take their argument with or without parentheses
+#[derive(Clone, Debug)]
+pub struct Expression {
+    pub source: String,
+    root: Node
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Expression) -> bool {
+        self.source == other.source
+    }
+}
+
+// what went wrong where, shown as the formula with a caret under the spot
+#[derive(Clone, Debug)]
+pub struct FormulaError {
+    pub source: String,
+    // in chars
+    pub column: usize,
+    pub message: String
+}
+
+impl fmt::Display for FormulaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.source)?;
+        write!(f, "{}^ {}", " ".repeat(self.column), self.message)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Z,
+    C,
+    Constant([f64; 2]),
+    Call(Function, Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Function {
+    Neg,
+    Abs,
+    Re,
+    Im,
+    Conj,
+    Sqrt,
+    Exp,
+    Log,
+    Sin,
+    Cos,
+    Tan,
+    Sinh,
+    Cosh,
+    Tanh
+}
+
+const FUNCTIONS: [(&str, Function); 13] = [
+    ("abs", Function::Abs),
+    ("re", Function::Re),
+    ("im", Function::Im),
+    ("conj", Function::Conj),
+    ("sqrt", Function::Sqrt),
+    ("exp", Function::Exp),
+    ("log", Function::Log),
+    ("sin", Function::Sin),
+    ("cos", Function::Cos),
+    ("tan", Function::Tan),
+    ("sinh", Function::Sinh),
+    ("cosh", Function::Cosh),
+    ("tanh", Function::Tanh),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow
+}
+
+// powers with an integer exponent up to this are multiplied out, the rest go through log
+const MAX_INTEGER_POWER: f64 = 64.0;
+
+// deepest nesting of parentheses, bars, signs, powers and function arguments. the parser
+// recurses once per level, this leaves room in a debug build on a 2 MiB thread stack
+const MAX_DEPTH: u32 = 128;
+
+// marks around the iteration step in sample.glsl, see `specialize`
+const BEGIN_MARK: &str = "// formula begin";
+const END_MARK: &str = "// formula end";
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Expression, FormulaError> {
+        let tokens = tokenize(source)?;
+
+        let mut parser = Parser {
+            source,
+            tokens,
+            next: 0,
+            bars: 0,
+            depth: 0
+        };
+
+        let root = parser.expression()?;
+
+        if let Some((_, token)) = parser.tokens.get(parser.next) {
+            return Err(parser.error(format!("expected an operator but found {}", token)));
+        }
+
+        Ok(Expression {
+            source: source.to_string(),
+            root
+        })
+    }
+
+    // one step of the iteration
+    pub fn step(&self, z: [f64; 2], c: [f64; 2]) -> [f64; 2] {
+        eval(&self.root, z, c)
+    }
+
+    // sampling source, see `mandelbrot::SAMPLE_SOURCE`, with the step between its formula
+    // marks replaced by this formula. the generated code carries dz/dc along as dual numbers,
+    // so the exterior distance estimate keeps working. the interior one would need second
+    // derivatives and stays 0
+    pub fn specialize(&self, sample: &str) -> Result<String, String> {
+        let begin = sample.find(BEGIN_MARK).ok_or("no formula begin mark")?;
+        let end = sample.find(END_MARK).ok_or("no formula end mark")?;
+
+        let generated = format!(
+            "{}\n// {}\nvec4 formula(vec4 z, vec4 c) {{\n    return {};\n}}\n{}",
+            DUAL_GLSL,
+            self.source.replace('\n', " "),
+            glsl(&self.root),
+            STEP_GLSL,
+        );
+
+        Ok(format!("{}{}\n{}", &sample[..begin], generated, &sample[end..]))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(char)
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "the number {}", value),
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Symbol(symbol) => write!(f, "'{}'", symbol),
+        }
+    }
+}
+
+// tokens with the column they start at
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, FormulaError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let ch = chars[i];
+
+        if ch.is_whitespace() {
+            i += 1;
+        } else if ch.is_ascii_digit() || ch == '.' {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+
+            let text: String = chars[start..i].iter().collect();
+
+            let value: f64 = text.parse().map_err(|_| FormulaError {
+                source: source.to_string(),
+                column: start,
+                message: format!("'{}' isn't a number", text),
+            })?;
+
+            // enough digits parse to inf, which no kernel compiles
+            if !value.is_finite() {
+                return Err(FormulaError {
+                    source: source.to_string(),
+                    column: start,
+                    message: format!("'{}' is too big a number", text),
+                });
+            }
+
+            tokens.push((start, Token::Number(value)));
+        } else if ch.is_alphabetic() || ch == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            tokens.push((start, Token::Name(chars[start..i].iter().collect())));
+        } else if "+-*/^()|".contains(ch) {
+            i += 1;
+            tokens.push((start, Token::Symbol(ch)));
+        } else {
+            return Err(FormulaError {
+                source: source.to_string(),
+                column: start,
+                message: format!("unexpected '{}'", ch),
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+// recursive descent, loosest binding first: + -, then * / and juxtaposition, then unary
+// minus, then ^ which groups to the right
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    // open |, a | where an operator could go closes the innermost one
+    bars: u32,
+    // subexpressions being parsed inside each other, see `MAX_DEPTH`
+    depth: u32
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).map(|(_, token)| token.clone());
+        self.next += 1;
+
+        token
+    }
+
+    fn error(&self, message: String) -> FormulaError {
+        let column = match self.tokens.get(self.next) {
+            Some((column, _)) => *column,
+            None => self.source.chars().count(),
+        };
+
+        FormulaError {
+            source: self.source.to_string(),
+            column,
+            message
+        }
+    }
+
+    // one level deeper, see `MAX_DEPTH`. `leave` goes back up
+    fn enter(&mut self) -> Result<(), FormulaError> {
+        self.depth += 1;
+
+        if self.depth > MAX_DEPTH {
+            return Err(self.error(format!("nested deeper than {} levels", MAX_DEPTH)));
+        }
+
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), FormulaError> {
+        match self.peek() {
+            Some(Token::Symbol(found)) if *found == symbol => {
+                self.next += 1;
+                Ok(())
+            }
+            Some(token) => Err(self.error(format!("expected '{}' but found {}", symbol, token))),
+            None => Err(self.error(format!("expected '{}' but the formula ends", symbol))),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node, FormulaError> {
+        let mut node = self.term()?;
+
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Symbol('+')) => Operator::Add,
+                Some(Token::Symbol('-')) => Operator::Sub,
+                _ => return Ok(node),
+            };
+
+            self.next += 1;
+            node = Node::Binary(operator, Box::new(node), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, FormulaError> {
+        let mut node = self.unary()?;
+
+        loop {
+            let (operator, right) = match self.peek() {
+                Some(Token::Symbol('*')) => {
+                    self.next += 1;
+                    (Operator::Mul, self.unary()?)
+                }
+                Some(Token::Symbol('/')) => {
+                    self.next += 1;
+                    (Operator::Div, self.unary()?)
+                }
+                // side by side, `2z` or `i|im z|`
+                Some(Token::Number(_)) | Some(Token::Name(_)) | Some(Token::Symbol('(')) => (Operator::Mul, self.power()?),
+                Some(Token::Symbol('|')) if self.bars == 0 => (Operator::Mul, self.power()?),
+                _ => return Ok(node),
+            };
+
+            node = Node::Binary(operator, Box::new(node), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, FormulaError> {
+        match self.peek() {
+            Some(Token::Symbol('-')) => {
+                self.next += 1;
+
+                self.enter()?;
+                let node = Node::Call(Function::Neg, Box::new(self.unary()?));
+                self.leave();
+
+                Ok(node)
+            }
+            Some(Token::Symbol('+')) => {
+                self.next += 1;
+
+                self.enter()?;
+                let node = self.unary()?;
+                self.leave();
+
+                Ok(node)
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> Result<Node, FormulaError> {
+        let base = self.primary()?;
+
+        if self.peek() != Some(&Token::Symbol('^')) {
+            return Ok(base);
+        }
+
+        self.next += 1;
+
+        self.enter()?;
+        let exponent = self.unary()?;
+        self.leave();
+
+        Ok(Node::Binary(Operator::Pow, Box::new(base), Box::new(exponent)))
+    }
+
+    fn primary(&mut self) -> Result<Node, FormulaError> {
+        let start = self.next;
+
+        let node = match self.advance() {
+            Some(Token::Number(value)) => Node::Constant([value, 0.0]),
+            Some(Token::Name(name)) => match name.as_str() {
+                "z" => Node::Z,
+                "c" => Node::C,
+                "i" => Node::Constant([0.0, 1.0]),
+                "pi" => Node::Constant([PI, 0.0]),
+                _ => {
+                    let function = match FUNCTIONS.iter().find(|(function, _)| *function == name) {
+                        Some((_, function)) => *function,
+                        None => {
+                            self.next = start;
+
+                            let names: Vec<&str> = FUNCTIONS.iter().map(|(function, _)| *function).collect();
+
+                            return Err(self.error(format!(
+                                "unknown name '{}', expected z, c, i, pi or one of {}",
+                                name,
+                                names.join(", "),
+                            )));
+                        }
+                    };
+
+                    // `sin(z)^2` squares the sine, `re z^2` takes the real part of the square
+                    self.enter()?;
+                    let argument = if self.peek() == Some(&Token::Symbol('(')) { self.primary()? } else { self.power()? };
+                    self.leave();
+
+                    Node::Call(function, Box::new(argument))
+                }
+            },
+            Some(Token::Symbol('(')) => {
+                self.enter()?;
+                let node = self.expression()?;
+                self.expect(')')?;
+                self.leave();
+
+                node
+            }
+            Some(Token::Symbol('|')) => {
+                self.enter()?;
+                self.bars += 1;
+                let node = self.expression()?;
+                self.expect('|')?;
+                self.bars -= 1;
+                self.leave();
+
+                Node::Call(Function::Abs, Box::new(node))
+            }
+            Some(token) => {
+                self.next = start;
+
+                return Err(self.error(format!("expected a number, a name, '(' or '|' but found {}", token)));
+            }
+            None => return Err(self.error("expected a number, a name, '(' or '|' but the formula ends".to_string())),
+        };
+
+        Ok(node)
+    }
+}
+
+// integer exponent small enough to multiply out
+fn integer_power(node: &Node) -> Option<i32> {
+    match node {
+        Node::Constant([n, im]) if *im == 0.0 && n.fract() == 0.0 && n.abs() <= MAX_INTEGER_POWER => Some(*n as i32),
+        // `z^-2`, the minus is parsed as a negation
+        Node::Call(Function::Neg, argument) => integer_power(argument).map(|n| -n),
+        _ => None,
+    }
+}
+
+fn eval(node: &Node, z: [f64; 2], c: [f64; 2]) -> [f64; 2] {
+    match node {
+        Node::Z => z,
+        Node::C => c,
+        Node::Constant(value) => *value,
+        Node::Call(function, argument) => {
+            let a = eval(argument, z, c);
+
+            match function {
+                Function::Neg => [-a[0], -a[1]],
+                Function::Abs => [modulus(a), 0.0],
+                Function::Re => [a[0], 0.0],
+                Function::Im => [a[1], 0.0],
+                Function::Conj => [a[0], -a[1]],
+                Function::Sqrt => exp(scale(log(a), 0.5)),
+                Function::Exp => exp(a),
+                Function::Log => log(a),
+                Function::Sin => sin(a),
+                Function::Cos => cos(a),
+                Function::Tan => div(sin(a), cos(a)),
+                Function::Sinh => sinh(a),
+                Function::Cosh => cosh(a),
+                Function::Tanh => div(sinh(a), cosh(a)),
+            }
+        }
+        Node::Binary(operator, left, right) => {
+            let a = eval(left, z, c);
+
+            if let (Operator::Pow, Some(n)) = (operator, integer_power(right)) {
+                return powi(a, n);
+            }
+
+            let b = eval(right, z, c);
+
+            match operator {
+                Operator::Add => [a[0] + b[0], a[1] + b[1]],
+                Operator::Sub => [a[0] - b[0], a[1] - b[1]],
+                Operator::Mul => mul(a, b),
+                Operator::Div => div(a, b),
+                Operator::Pow => if a == [0.0, 0.0] { a } else { exp(mul(b, log(a))) },
+            }
+        }
+    }
+}
+
+fn modulus(a: [f64; 2]) -> f64 {
+    (a[0] * a[0] + a[1] * a[1]).sqrt()
+}
+
+fn scale(a: [f64; 2], s: f64) -> [f64; 2] {
+    [a[0] * s, a[1] * s]
+}
+
+fn mul(a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    [a[0] * b[0] - a[1] * b[1], a[0] * b[1] + a[1] * b[0]]
+}
+
+fn div(a: [f64; 2], b: [f64; 2]) -> [f64; 2] {
+    let d = b[0] * b[0] + b[1] * b[1];
+
+    [(a[0] * b[0] + a[1] * b[1]) / d, (a[1] * b[0] - a[0] * b[1]) / d]
+}
+
+fn powi(a: [f64; 2], n: i32) -> [f64; 2] {
+    let mut result = [1.0, 0.0];
+
+    for _ in 0..n.abs() {
+        result = mul(result, a);
+    }
+
+    if n < 0 { div([1.0, 0.0], result) } else { result }
+}
+
+fn exp(a: [f64; 2]) -> [f64; 2] {
+    let r = a[0].exp();
+
+    [r * a[1].cos(), r * a[1].sin()]
+}
+
+fn log(a: [f64; 2]) -> [f64; 2] {
+    [modulus(a).ln(), a[1].atan2(a[0])]
+}
+
+fn sin(a: [f64; 2]) -> [f64; 2] {
+    [a[0].sin() * a[1].cosh(), a[0].cos() * a[1].sinh()]
+}
+
+fn cos(a: [f64; 2]) -> [f64; 2] {
+    [a[0].cos() * a[1].cosh(), -a[0].sin() * a[1].sinh()]
+}
+
+fn sinh(a: [f64; 2]) -> [f64; 2] {
+    [a[0].sinh() * a[1].cos(), a[0].cosh() * a[1].sin()]
+}
+
+fn cosh(a: [f64; 2]) -> [f64; 2] {
+    [a[0].cosh() * a[1].cos(), a[0].sinh() * a[1].sin()]
+}
+
+// glsl for a node over dual numbers, value in xy and its derivative in zw
+fn glsl(node: &Node) -> String {
+    match node {
+        Node::Z => "z".to_string(),
+        Node::C => "c".to_string(),
+        Node::Constant(value) => format!("vec4({:?}, {:?}, 0.0, 0.0)", value[0], value[1]),
+        Node::Call(Function::Neg, argument) => format!("(-{})", glsl(argument)),
+        Node::Call(function, argument) => format!("d_{}({})", format!("{:?}", function).to_lowercase(), glsl(argument)),
+        Node::Binary(operator, left, right) => {
+            let (a, b) = (glsl(left), glsl(right));
+
+            match operator {
+                Operator::Add => format!("({} + {})", a, b),
+                Operator::Sub => format!("({} - {})", a, b),
+                Operator::Mul => format!("d_mul({}, {})", a, b),
+                Operator::Div => format!("d_div({}, {})", a, b),
+                Operator::Pow => match integer_power(right) {
+                    Some(n) => format!("d_powi({}, {})", a, n),
+                    None => format!("d_pow({}, {})", a, b),
+                },
+            }
+        }
+    }
+}
+
+// dual number arithmetic the generated formula is written in, one d_ function per
+// `Function`. cmul and cdiv come from sample.glsl
+const DUAL_GLSL: &str = "
+vec2 c_exp(vec2 a) {
+    return exp(a.x) * vec2(cos(a.y), sin(a.y));
+}
+
+vec2 c_log(vec2 a) {
+    return vec2(log(length(a)), atan(a.y, a.x));
+}
+
+vec2 c_sin(vec2 a) {
+    return vec2(sin(a.x) * cosh(a.y), cos(a.x) * sinh(a.y));
+}
+
+vec2 c_cos(vec2 a) {
+    return vec2(cos(a.x) * cosh(a.y), -sin(a.x) * sinh(a.y));
+}
+
+vec2 c_sinh(vec2 a) {
+    return vec2(sinh(a.x) * cos(a.y), cosh(a.x) * sin(a.y));
+}
+
+vec2 c_cosh(vec2 a) {
+    return vec2(cosh(a.x) * cos(a.y), sinh(a.x) * sin(a.y));
+}
+
+vec4 d_mul(vec4 a, vec4 b) {
+    return vec4(cmul(a.xy, b.xy), cmul(a.xy, b.zw) + cmul(a.zw, b.xy));
+}
+
+vec4 d_div(vec4 a, vec4 b) {
+    vec2 q = cdiv(a.xy, b.xy);
+
+    return vec4(q, cdiv(a.zw - cmul(q, b.zw), b.xy));
+}
+
+vec4 d_exp(vec4 a) {
+    vec2 e = c_exp(a.xy);
+
+    return vec4(e, cmul(e, a.zw));
+}
+
+vec4 d_log(vec4 a) {
+    return vec4(c_log(a.xy), cdiv(a.zw, a.xy));
+}
+
+vec4 d_sqrt(vec4 a) {
+    vec2 r = c_exp(0.5 * c_log(a.xy));
+
+    return vec4(r, cdiv(a.zw, 2.0 * r));
+}
+
+vec4 d_sin(vec4 a) {
+    return vec4(c_sin(a.xy), cmul(c_cos(a.xy), a.zw));
+}
+
+vec4 d_cos(vec4 a) {
+    return vec4(c_cos(a.xy), -cmul(c_sin(a.xy), a.zw));
+}
+
+vec4 d_tan(vec4 a) {
+    return d_div(d_sin(a), d_cos(a));
+}
+
+vec4 d_sinh(vec4 a) {
+    return vec4(c_sinh(a.xy), cmul(c_cosh(a.xy), a.zw));
+}
+
+vec4 d_cosh(vec4 a) {
+    return vec4(c_cosh(a.xy), cmul(c_sinh(a.xy), a.zw));
+}
+
+vec4 d_tanh(vec4 a) {
+    return d_div(d_sinh(a), d_cosh(a));
+}
+
+// not holomorphic, the derivative is taken along the direction a moves in
+vec4 d_abs(vec4 a) {
+    float r = length(a.xy);
+
+    return vec4(r, 0.0, r > 0.0 ? dot(a.xy, a.zw) / r : 0.0, 0.0);
+}
+
+vec4 d_re(vec4 a) {
+    return vec4(a.x, 0.0, a.z, 0.0);
+}
+
+vec4 d_im(vec4 a) {
+    return vec4(a.y, 0.0, a.w, 0.0);
+}
+
+vec4 d_conj(vec4 a) {
+    return vec4(a.x, -a.y, a.z, -a.w);
+}
+
+vec4 d_powi(vec4 a, int n) {
+    vec4 r = vec4(1.0, 0.0, 0.0, 0.0);
+
+    for (int i = 0; i < abs(n); i += 1) {
+        r = d_mul(r, a);
+    }
+
+    return n < 0 ? d_div(vec4(1.0, 0.0, 0.0, 0.0), r) : r;
+}
+
+vec4 d_pow(vec4 a, vec4 b) {
+    if (a.xy == vec2(0.0)) {
+        return vec4(0.0);
+    }
+
+    return d_exp(d_mul(b, d_log(a)));
+}
+";
+
+// `step` and `cycle` of sample.glsl on top of the generated formula
+const STEP_GLSL: &str = "
+// one step of z and of dz/dc along with it
+void step(inout vec2 z, inout vec2 dz, vec2 c) {
+    vec4 next = formula(vec4(z, dz), vec4(c, 1.0, 0.0));
+
+    z = next.xy;
+    dz = next.zw;
+}
+
+// multiplier of the cycle through z, from the derivative by z this time
+void cycle(inout Sample s, vec2 c, uint period) {
+    vec2 z = s.z;
+    vec2 dz = vec2(1.0, 0.0);
+
+    for (uint i = 0; i < period; i += 1) {
+        vec4 next = formula(vec4(z, 1.0, 0.0), vec4(c, 0.0, 0.0));
+
+        dz = cmul(next.zw, dz);
+        z = next.xy;
+    }
+
+    s.multiplier = length(dz);
+}
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::engine::shaders::mandelbrot;
+
+    const Z: [f64; 2] = [0.3, 0.2];
+    const C: [f64; 2] = [0.1, -0.4];
+
+    fn step(source: &str) -> [f64; 2] {
+        Expression::parse(source).unwrap().step(Z, C)
+    }
+
+    fn assert_close(found: [f64; 2], expected: [f64; 2]) {
+        let error = (found[0] - expected[0]).abs().max((found[1] - expected[1]).abs());
+
+        assert!(error < 1e-12, "{:?} isn't {:?}", found, expected);
+    }
+
+    fn error(source: &str) -> FormulaError {
+        Expression::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn examples() {
+        // z^3 = (-0.009, 0.046), sin(z) = (0.30137.., 0.19352..)
+        assert_close(step("z^3 + c*sin(z)"), [0.09808248576376828, -0.055345772391345305]);
+        // (0.3 + 0.2i)^2 + c
+        assert_close(step("(|re z| + i|im z|)^2 + c"), [0.15, -0.28]);
+    }
+
+    #[test]
+    fn mandelbrot() {
+        assert_close(step("z^2 + c"), [0.15, -0.28]);
+        assert_close(step("z*z+c"), [0.15, -0.28]);
+    }
+
+    #[test]
+    fn precedence() {
+        // the minus applies to the power, not to z
+        assert_close(step("-z^2"), [-0.05, -0.12]);
+        assert_close(step("(-z)^2"), [0.05, 0.12]);
+        // side by side binds looser than ^
+        assert_close(step("2z^2"), [0.1, 0.24]);
+        assert_close(step("2z^2 + c"), [0.2, -0.16]);
+        // ^ groups to the right, z^(2^3) and not (z^2)^3
+        assert_close(step("z^2^3"), step("z^8"));
+        assert_close(step("z^8"), [-0.000002389999999999901, -0.00028559999999999995]);
+        // bars inside bars, ||z| - 1| = 1 - |z|
+        assert_close(step("||z| - 1|"), [1.0 - 0.13f64.sqrt(), 0.0]);
+        assert_close(step("|z|^2 + c"), [0.23, -0.4]);
+    }
+
+    #[test]
+    fn powers() {
+        // 1 / (0.05 + 0.12i) + c, multiplied out
+        assert_close(step("z^-2 + c"), [3.058579881656805, -7.5005917159763325]);
+        // not an integer, exp(2.5 log z) + c
+        assert_close(step("z^2.5+c"), [0.10785435088133136, -0.3223360390672284]);
+    }
+
+    #[test]
+    fn glsl_powers() {
+        let sample = mandelbrot::SAMPLE_SOURCE;
+
+        let integer = Expression::parse("z^-2 + c").unwrap().specialize(sample).unwrap();
+        assert!(integer.contains("d_powi(z, -2)"));
+
+        let real = Expression::parse("z^2.5 + c").unwrap().specialize(sample).unwrap();
+        assert!(real.contains("d_pow(z, vec4(2.5, 0.0, 0.0, 0.0))"));
+
+        // the mandelbrot step between the marks is gone
+        assert_eq!(real.matches(BEGIN_MARK).count(), 0);
+        assert_eq!(real.matches(END_MARK).count(), 1);
+    }
+
+    #[test]
+    fn unknown_name() {
+        let e = error("z^2 + foo");
+
+        assert_eq!(e.column, 6);
+        assert!(e.message.starts_with("unknown name 'foo', expected z, c, i, pi or one of abs, re,"), "{}", e.message);
+    }
+
+    #[test]
+    fn missing_parenthesis() {
+        let e = error("(z^2 + c");
+
+        assert_eq!(e.column, 8);
+        assert_eq!(e.message, "expected ')' but the formula ends");
+    }
+
+    #[test]
+    fn nesting() {
+        let nested = |depth: usize| format!("{}z{}^2 + c", "(".repeat(depth), ")".repeat(depth));
+
+        assert_close(step(&nested(100)), [0.15, -0.28]);
+
+        // would run the parser out of stack without the limit
+        for source in [nested(5000), format!("{}z{}", "|".repeat(5000), "|".repeat(5000)), format!("{}z", "-".repeat(5000))] {
+            let e = error(&source);
+
+            assert_eq!(e.message, format!("nested deeper than {} levels", MAX_DEPTH));
+        }
+    }
+
+    #[test]
+    fn huge_number() {
+        let e = error(&format!("z^2 + {}", "9".repeat(400)));
+
+        assert_eq!(e.column, 6);
+        assert!(e.message.ends_with("is too big a number"), "{}", e.message);
+    }
+
+    #[test]
+    fn trailing_operator() {
+        let e = error("z^");
+
+        assert_eq!(e.column, 2);
+        assert_eq!(e.message, "expected a number, a name, '(' or '|' but the formula ends");
+        assert_eq!(e.to_string(), "z^\n  ^ expected a number, a name, '(' or '|' but the formula ends");
+    }
+}
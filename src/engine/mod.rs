@@ -1,4 +1,12 @@
 pub mod engine;
+pub mod device;
+pub mod formula;
+pub mod offscreen;
+pub mod view;
+mod cpu;
 mod renderer;
 mod camera;
-mod shaders;
\ No newline at end of file
+mod orbit;
+mod shaders;
+
+pub use self::renderer::coloring::{Coloring, Interior};
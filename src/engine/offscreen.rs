@@ -0,0 +1,221 @@
+use std::sync::Arc;
+
+use image::RgbaImage;
+
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage};
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, CopyImageToBufferInfo, PrimaryCommandBufferAbstract};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::{ComputePipeline, Pipeline};
+use vulkano::sync::GpuFuture;
+use vulkano_util::context::VulkanoContext;
+
+use super::cpu;
+use super::device::{self, DeviceId};
+use super::renderer::histogram::Histogram;
+use super::renderer::screenshot;
+use super::renderer::shader_watch::{self, Stage};
+use super::shaders::mandelbrot;
+use super::view::{Formula, View};
+
+// what draws the images of an `Offscreen`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    // the window's kernels on a vulkan device
+    Gpu,
+    // f64 on the cpu's threads, no vulkan needed, see `cpu::render`. it only knows the
+    // grayscale iteration count: the view's coloring and interior are ignored, points that
+    // never escape come out white and nothing is supersampled
+    Cpu
+}
+
+// renders views into images without a window, one at a time and blocking until each is done
+pub struct Offscreen {
+    gpu: Option<Gpu>
+}
+
+struct Gpu {
+    context: VulkanoContext,
+
+    memory_allocator: StandardMemoryAllocator,
+    command_buffer_allocator: StandardCommandBufferAllocator,
+    descriptor_set_allocator: StandardDescriptorSetAllocator,
+
+    compute_pipeline: Arc<ComputePipeline>,
+    colorize_pipeline: Arc<ComputePipeline>,
+    histogram: Histogram,
+
+    // what compute_pipeline iterates, rebuilt when a view asks for another one
+    formula: Formula
+}
+
+impl Offscreen {
+    // the gpu backend goes on the physical device like `Engine::new`, without needing a display
+    pub fn new(backend: Backend, device: Option<DeviceId>) -> Offscreen {
+        let gpu = match backend {
+            Backend::Gpu => Some(Gpu::new(device)),
+            Backend::Cpu => None,
+        };
+
+        Offscreen { gpu }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self.gpu {
+            Some(_) => Backend::Gpu,
+            None => Backend::Cpu,
+        }
+    }
+
+    // the view framed like a window of this size, see `View`. the gpu backend fails on a
+    // size without pixels or past the device's largest image, and on a formula its kernel
+    // doesn't build with. the cpu one draws every view in grayscale iterations, see
+    // `Backend::Cpu`
+    pub fn render(&mut self, view: &View, size: [u32; 2]) -> Result<RgbaImage, String> {
+        match &mut self.gpu {
+            Some(gpu) => gpu.render(view, size),
+            None => Ok(cpu::render(view, size)),
+        }
+    }
+}
+
+impl Gpu {
+    fn new(device: Option<DeviceId>) -> Gpu {
+        let context = VulkanoContext::new(device::offscreen_config(device));
+        let device = context.device().clone();
+
+        let memory_allocator = StandardMemoryAllocator::new_default(device.clone());
+        let command_buffer_allocator =
+            StandardCommandBufferAllocator::new(device.clone(), Default::default());
+        let descriptor_set_allocator = StandardDescriptorSetAllocator::new(device.clone());
+
+        let compute_shader = mandelbrot::cs::load(device.clone()).unwrap();
+
+        let compute_pipeline = ComputePipeline::new(
+            device.clone(),
+            compute_shader.entry_point("main").unwrap(),
+            &(),
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        let colorize_shader = mandelbrot::colorize::load(device.clone()).unwrap();
+
+        let colorize_pipeline = ComputePipeline::new(
+            device.clone(),
+            colorize_shader.entry_point("main").unwrap(),
+            &(),
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        let histogram = Histogram::new(device.clone(), &memory_allocator, &descriptor_set_allocator);
+
+        Gpu {
+            context,
+
+            memory_allocator,
+            command_buffer_allocator,
+            descriptor_set_allocator,
+
+            compute_pipeline,
+            colorize_pipeline,
+            histogram,
+
+            formula: Formula::Mandelbrot
+        }
+    }
+
+    fn render(&mut self, view: &View, size: [u32; 2]) -> Result<RgbaImage, String> {
+        let max_dimension = self.context.device().physical_device().properties().max_image_dimension2_d;
+
+        if size[0] == 0 || size[1] == 0 {
+            return Err(format!("{}x{} has no pixels", size[0], size[1]));
+        }
+
+        if size[0] > max_dimension || size[1] > max_dimension {
+            return Err(format!("{}x{} is bigger than the device's images go, {} pixels a side", size[0], size[1], max_dimension));
+        }
+
+        if view.formula != self.formula {
+            self.set_formula(&view.formula)?;
+        }
+
+        let queue = self.context.compute_queue().clone();
+        let camera = view.camera([size[0] as f32, size[1] as f32]);
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            &self.command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let image = screenshot::record_offscreen(
+            &mut builder,
+            &self.memory_allocator,
+            &self.descriptor_set_allocator,
+            self.compute_pipeline.clone(),
+            self.colorize_pipeline.clone(),
+            &mut self.histogram,
+            queue.queue_family_index(),
+            &camera,
+            size,
+        );
+
+        let buffer = Buffer::new_slice::<u8>(
+            &self.memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Download,
+                ..Default::default()
+            },
+            size[0] as u64 * size[1] as u64 * 4,
+        )
+        .unwrap();
+
+        builder
+            .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(image.image().clone(), buffer.clone()))
+            .unwrap();
+
+        builder
+            .build()
+            .unwrap()
+            .execute(queue)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let pixels = buffer.read().unwrap().to_vec();
+
+        Ok(RgbaImage::from_raw(size[0], size[1], pixels).unwrap())
+    }
+
+    // the error is the shader compiler's, the kernel stays as it was
+    fn set_formula(&mut self, formula: &Formula) -> Result<(), String> {
+        let device = self.context.device().clone();
+
+        let (_, module) = shader_watch::compile_stage(Stage::Compute, mandelbrot::CS_SOURCE, mandelbrot::SAMPLE_SOURCE, formula, device.clone())?;
+
+        self.compute_pipeline = ComputePipeline::with_pipeline_layout(
+            device,
+            module.entry_point("main").unwrap(),
+            &(),
+            self.compute_pipeline.layout().clone(),
+            None,
+        )
+        .map_err(|e| format!("compute pipeline: {}", e))?;
+
+        self.formula = formula.clone();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+// cpu side orbit of a single point, used by the orbit overlay
+
+use super::view::Formula;
+
+// same bailout as the `cs` kernel
+const ESCAPE_RADIUS: f64 = 4.0;
+
+// how close two orbit points have to be to count as the same point of a cycle
+const PERIOD_EPSILON: f64 = 1e-9;
+const MAX_PERIOD: usize = 1024;
+
+pub struct Orbit {
+    pub c: [f64; 2],
+
+    // z values in iteration order, starting at z = c like the kernel does
+    pub points: Vec<[f64; 2]>,
+
+    // iteration count the kernel would report for this point, none if it never escaped
+    pub escape_iter: Option<u32>,
+    // length of the cycle the orbit settled into, none if it escaped or hasn't settled
+    pub period: Option<u32>
+}
+
+impl Orbit {
+    pub fn compute(c: [f64; 2], max_iters: u32, formula: &Formula) -> Orbit {
+        let mut points = Vec::with_capacity(max_iters as usize + 1);
+        let mut z = c;
+
+        points.push(z);
+
+        let mut escape_iter = None;
+
+        for i in 0..max_iters {
+            z = formula.step(z, c);
+
+            points.push(z);
+
+            if abs(z) > ESCAPE_RADIUS {
+                escape_iter = Some(i);
+                break;
+            }
+        }
+
+        let period = match escape_iter {
+            Some(_) => None,
+            None => detect_period(&points),
+        };
+
+        Orbit {
+            c,
+            points,
+            escape_iter,
+            period
+        }
+    }
+
+    pub fn final_abs(&self) -> f64 {
+        abs(*self.points.last().unwrap())
+    }
+}
+
+fn abs(z: [f64; 2]) -> f64 {
+    (z[0] * z[0] + z[1] * z[1]).sqrt()
+}
+
+// smallest p where the last point comes back around within epsilon
+fn detect_period(points: &[[f64; 2]]) -> Option<u32> {
+    let last = *points.last()?;
+
+    for p in 1..MAX_PERIOD.min(points.len()) {
+        let earlier = points[points.len() - 1 - p];
+
+        if abs([last[0] - earlier[0], last[1] - earlier[1]]) < PERIOD_EPSILON {
+            return Some(p as u32);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,639 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CopyBufferToImageInfo, CopyImageInfo, CopyImageToBufferInfo, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::Device;
+use vulkano::image::{ImageUsage, StorageImage};
+use vulkano::image::view::ImageView;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
+
+use super::coloring::{Coloring, Interior};
+use super::frame::{self, TargetSets, ORBITS_FORMAT, SAMPLES_FORMAT};
+use super::render::RenderCamera;
+
+use crate::engine::shaders::cache;
+
+// side of a tile in pixels, must match the compose kernel
+const TILE_PIXELS: u32 = 128;
+// the level 0 tile covers [-4, 4] on both axes, every level halves the side
+const ROOT_SIZE: f32 = 8.0;
+// past this the tiles get smaller than f32 can tell apart
+const MAX_LEVEL: i32 = 18;
+
+// resident tiles on the gpu, in tiles
+const ATLAS_COLUMNS: u32 = 32;
+const ATLAS_ROWS: u32 = 16;
+const SLOTS: usize = (ATLAS_COLUMNS * ATLAS_ROWS) as usize;
+
+// uploads per frame, so a view full of spilled tiles doesn't stall the ui. computed tiles
+// are limited by the caller's `Budget`
+const UPLOAD_PER_FRAME: usize = 64;
+
+// host copies kept in memory, about 200 mb. past this the oldest go to disk, or away
+// without a spill directory
+const MEMORY_TILES: usize = 512;
+// tiles kept in the spill directory, about 1.6 gb. past this the least recently spilled or
+// read ones are deleted
+const SPILL_TILES: usize = 4096;
+
+// a tile as it's kept on the host, the samples followed by the orbits
+const SAMPLE_BYTES: u64 = (TILE_PIXELS * TILE_PIXELS * 16) as u64;
+const TILE_BYTES: u64 = SAMPLE_BYTES + (TILE_PIXELS * TILE_PIXELS * 8) as u64;
+
+// goes into the spill file names, bump it whenever what the bytes of a tile mean changes
+// without the kernel source changing, e.g. the sample formats or their channels
+const FORMAT_VERSION: u32 = 1;
+
+// a tile of the quadtree and the limit and kernel its samples were taken with
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct TileKey {
+    pub level: i32,
+    // in tiles from the lower corner of the level 0 tile
+    pub x: i32,
+    pub y: i32,
+    pub max_iters: u32,
+    pub supersample: bool,
+    // see `kernel_key`
+    pub kernel: u64
+}
+
+impl TileKey {
+    // what the `cs` kernel renders the tile with on a square image, pixel k lands
+    // k / TILE_PIXELS of the side from its lower corner
+    fn camera(&self) -> RenderCamera {
+        let side = tile_side(self.level);
+
+        RenderCamera {
+            translation: [
+                -ROOT_SIZE / 2.0 + (self.x as f32 + 0.5) * side,
+                -ROOT_SIZE / 2.0 + (self.y as f32 + 0.5) * side,
+            ],
+            zoom: 4.0 / side,
+            rotation: 0.0,
+            max_iters: self.max_iters,
+            coloring: if self.supersample { Coloring::AntiAliased } else { Coloring::Iterations },
+            interior: Interior::Flat
+        }
+    }
+
+    fn file_name(&self) -> String {
+        format!(
+            "v{}_{:016x}_{}_{}_{}_{}_{}.tile",
+            FORMAT_VERSION, self.kernel, self.level, self.x, self.y, self.max_iters, self.supersample as u8,
+        )
+    }
+}
+
+// tells tiles of different `cs` kernels apart, a hash of the glsl the kernel was compiled
+// from, formula spliced in. fnv-1a, unlike std's hasher it stays the same across runs and
+// rust versions, which the spill files outlive
+pub fn kernel_key(source: &str) -> u64 {
+    source.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+fn tile_side(level: i32) -> f32 {
+    ROOT_SIZE * 0.5f32.powi(level)
+}
+
+// limits are rounded up to 4 steps per doubling, so views at nearby zooms whose automatic
+// limits differ a little still share tiles
+fn cache_iters(max_iters: u32) -> u32 {
+    let step = (1u32 << (31 - max_iters.max(8).leading_zeros())) / 4;
+
+    (max_iters + step - 1) / step * step
+}
+
+// coarsest level whose tiles are at least as fine as a pixel of this size
+fn level_for(pixel: f32) -> i32 {
+    (ROOT_SIZE / (TILE_PIXELS as f32 * pixel)).log2().ceil() as i32
+}
+
+// whether the cache has a level fine enough for the view, deeper views go through the
+// normal tiles instead
+pub fn covers(camera: &RenderCamera, dims: [u32; 2]) -> bool {
+    let pixel = camera.pixel_size(dims[1] as f32);
+
+    level_for(pixel) <= MAX_LEVEL
+}
+
+// tiles of a level covering the view, lower corner and size in tiles
+fn grid(camera: &RenderCamera, dims: [f32; 2], level: i32) -> ([i32; 2], [u32; 2]) {
+    let corners = [[0.0, 0.0], [dims[0], 0.0], dims, [0.0, dims[1]]].map(|pixel| camera.to_complex(pixel, dims));
+
+    let side = tile_side(level);
+    let cell = |v: f32| ((v + ROOT_SIZE / 2.0) / side).floor() as i32;
+
+    let min = [0, 1].map(|axis| corners.iter().map(|c| cell(c[axis])).min().unwrap());
+    let max = [0, 1].map(|axis| corners.iter().map(|c| cell(c[axis])).max().unwrap());
+
+    (min, [(max[0] - min[0] + 1) as u32, (max[1] - min[1] + 1) as u32])
+}
+
+// quadtree of finished tiles keyed by region, level and limit. the view is composed from
+// tiles at the level closest to its pixel size, only tiles that were never seen get
+// computed. tiles live in a gpu atlas while they're drawn, in host memory after that and
+// optionally on disk once memory is full, so going back to an earlier view is instant
+pub struct TileCache {
+    compose_pipeline: Arc<ComputePipeline>,
+
+    atlas_samples: Arc<ImageView<StorageImage>>,
+    atlas_orbits: Arc<ImageView<StorageImage>>,
+
+    // one tile is computed here and then copied into the atlas
+    scratch_samples: Arc<ImageView<StorageImage>>,
+    scratch_orbits: Arc<ImageView<StorageImage>>,
+    scratch_set: Arc<PersistentDescriptorSet>,
+
+    // atlas slot of every resident tile and the frame it was last drawn in
+    resident: HashMap<TileKey, (u32, u64)>,
+    free: Vec<u32>,
+
+    // host copies, oldest first
+    memory: HashMap<TileKey, Vec<u8>>,
+    memory_order: VecDeque<TileKey>,
+    spill: Option<PathBuf>,
+    // file names in the spill directory, least recently used first
+    spilled: VecDeque<String>,
+    // readbacks of computed tiles the gpu may not be done with
+    downloads: Vec<(TileKey, Subbuffer<[u8]>)>,
+
+    frame: u64,
+    // whether the last view had tiles missing
+    pending: bool
+}
+
+impl TileCache {
+    pub fn new(device: Arc<Device>,
+            memory_allocator: &StandardMemoryAllocator,
+            descriptor_set_allocator: &StandardDescriptorSetAllocator,
+            compute_family: u32,
+            compute_pipeline: &ComputePipeline,
+            spill: Option<PathBuf>) -> TileCache {
+        let compose_shader = cache::compose::load(device.clone()).unwrap();
+
+        let compose_pipeline = ComputePipeline::new(
+            device.clone(),
+            compose_shader.entry_point("main").unwrap(),
+            &(),
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        let atlas_dims = [ATLAS_COLUMNS * TILE_PIXELS, ATLAS_ROWS * TILE_PIXELS];
+        let atlas_usage = ImageUsage::STORAGE | ImageUsage::TRANSFER_DST;
+
+        let atlas_samples = frame::new_image(memory_allocator, atlas_dims, SAMPLES_FORMAT, atlas_usage, &[compute_family]);
+        let atlas_orbits = frame::new_image(memory_allocator, atlas_dims, ORBITS_FORMAT, atlas_usage, &[compute_family]);
+
+        let scratch_dims = [TILE_PIXELS, TILE_PIXELS];
+        let scratch_usage = ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC;
+
+        let scratch_samples = frame::new_image(memory_allocator, scratch_dims, SAMPLES_FORMAT, scratch_usage, &[compute_family]);
+        let scratch_orbits = frame::new_image(memory_allocator, scratch_dims, ORBITS_FORMAT, scratch_usage, &[compute_family]);
+
+        let scratch_set = frame::raw_set(
+            descriptor_set_allocator,
+            compute_pipeline,
+            &scratch_samples,
+            &scratch_orbits,
+            frame::new_states(memory_allocator, 1),
+        );
+
+        let mut spilled = VecDeque::new();
+
+        if let Some(dir) = &spill {
+            if let Err(e) = fs::create_dir_all(dir) {
+                eprintln!("failed to create tile cache directory {}: {}", dir.display(), e);
+            }
+
+            spilled = spilled_tiles(dir);
+        }
+
+        TileCache {
+            compose_pipeline,
+
+            atlas_samples,
+            atlas_orbits,
+
+            scratch_samples,
+            scratch_orbits,
+            scratch_set,
+
+            resident: HashMap::new(),
+            free: (0..SLOTS as u32).rev().collect(),
+
+            memory: HashMap::new(),
+            memory_order: VecDeque::new(),
+            spill,
+            spilled,
+            downloads: Vec::new(),
+
+            frame: 0,
+            pending: false
+        }
+    }
+
+    pub fn is_pending(&self) -> bool {
+        return self.pending;
+    }
+
+    // the view left the cache, nothing is outstanding anymore
+    pub fn cancel(&mut self) {
+        self.pending = false;
+    }
+
+    // brings in or computes the tiles the view needs and composes them into the samples of
+    // the target sets, false while some are still missing. pixels of missing tiles keep
+    // whatever they held. only for views the cache `covers`, at most max_computed tiles are
+    // computed
+    pub fn record(&mut self,
+            builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            memory_allocator: &StandardMemoryAllocator,
+            descriptor_set_allocator: &StandardDescriptorSetAllocator,
+            compute_pipeline: Arc<ComputePipeline>,
+            sets: &TargetSets,
+            camera: &RenderCamera,
+            kernel: u64,
+            max_computed: usize) -> bool {
+        self.frame += 1;
+        self.collect_downloads();
+
+        let [width, height] = sets.dims();
+        let dims = [width as f32, height as f32];
+
+        let mut level = level_for(camera.pixel_size(dims[1]));
+        let (mut origin, mut size) = grid(camera, dims, level);
+
+        // a view too big for the atlas at its own level makes do with coarser tiles
+        while size[0] as usize * size[1] as usize > SLOTS {
+            level -= 1;
+            (origin, size) = grid(camera, dims, level);
+        }
+
+        let max_iters = cache_iters(camera.max_iters);
+        let supersample = camera.coloring.supersamples();
+        let side = tile_side(level);
+
+        let mut keys = Vec::new();
+
+        for y in 0..size[1] {
+            for x in 0..size[0] {
+                keys.push(([x, y], TileKey {
+                    level,
+                    x: origin[0] + x as i32,
+                    y: origin[1] + y as i32,
+                    max_iters,
+                    supersample,
+                    kernel
+                }));
+            }
+        }
+
+        // tiles near the middle of the view first
+        let middle = camera.to_complex([dims[0] / 2.0, dims[1] / 2.0], dims);
+        let distance = |key: &TileKey| {
+            let center = key.camera().translation;
+
+            (center[0] - middle[0]).powi(2) + (center[1] - middle[1]).powi(2)
+        };
+
+        keys.sort_by(|a, b| distance(&a.1).partial_cmp(&distance(&b.1)).unwrap());
+
+        // nothing the view needs gets evicted for another of its tiles
+        for (_, key) in &keys {
+            if let Some(entry) = self.resident.get_mut(key) {
+                entry.1 = self.frame;
+            }
+        }
+
+        let mut slots = vec![-1i32; keys.len()];
+        let mut computed = 0;
+        let mut uploaded = 0;
+        let mut missing = false;
+
+        for ([x, y], key) in &keys {
+            let index = (y * size[0] + x) as usize;
+
+            if let Some((slot, _)) = self.resident.get(key) {
+                slots[index] = *slot as i32;
+                continue;
+            }
+
+            let host = if uploaded < UPLOAD_PER_FRAME { self.host_copy(key) } else { None };
+
+            if host.is_none() && computed >= max_computed {
+                missing = true;
+                continue;
+            }
+
+            let slot = match self.take_slot() {
+                Some(slot) => slot,
+                None => {
+                    missing = true;
+                    continue;
+                }
+            };
+
+            match host {
+                Some(bytes) => {
+                    self.upload(builder, memory_allocator, slot, bytes);
+                    uploaded += 1;
+                }
+                None => {
+                    self.compute(builder, memory_allocator, compute_pipeline.clone(), slot, key);
+                    computed += 1;
+                }
+            }
+
+            self.resident.insert(*key, (slot, self.frame));
+            slots[index] = slot as i32;
+        }
+
+        let slot_buffer = Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            slots,
+        )
+        .unwrap();
+
+        let set = PersistentDescriptorSet::new(
+            descriptor_set_allocator,
+            self.compose_pipeline.layout().set_layouts().get(0).unwrap().clone(),
+            [
+                WriteDescriptorSet::image_view(0, self.atlas_samples.clone()),
+                WriteDescriptorSet::image_view(1, self.atlas_orbits.clone()),
+                WriteDescriptorSet::image_view(2, sets.samples.clone()),
+                WriteDescriptorSet::image_view(3, sets.orbits.clone()),
+                WriteDescriptorSet::buffer(4, slot_buffer),
+            ],
+        )
+        .unwrap();
+
+        let push_constants = cache::compose::PushConstants {
+            translation: camera.translation,
+            grid_origin: [
+                -ROOT_SIZE / 2.0 + origin[0] as f32 * side,
+                -ROOT_SIZE / 2.0 + origin[1] as f32 * side,
+            ],
+            grid_size: size,
+            scale: camera.zoom,
+            rotation: camera.rotation,
+            tile_side: side,
+            atlas_columns: ATLAS_COLUMNS,
+        };
+
+        builder
+            .bind_pipeline_compute(self.compose_pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.compose_pipeline.layout().clone(),
+                0,
+                set,
+            )
+            .push_constants(self.compose_pipeline.layout().clone(), 0, push_constants)
+            .dispatch([(width + 7) / 8, (height + 7) / 8, 1])
+            .unwrap();
+
+        self.pending = missing;
+
+        !missing
+    }
+
+    // top left pixel of an atlas slot
+    fn slot_corner(&self, slot: u32) -> [u32; 2] {
+        [(slot % ATLAS_COLUMNS) * TILE_PIXELS, (slot / ATLAS_COLUMNS) * TILE_PIXELS]
+    }
+
+    // a free atlas slot, or the one of the tile drawn longest ago. none if every slot
+    // holds a tile of this frame
+    fn take_slot(&mut self) -> Option<u32> {
+        if let Some(slot) = self.free.pop() {
+            return Some(slot);
+        }
+
+        let (key, slot) = self.resident
+            .iter()
+            .filter(|(_, (_, used))| *used < self.frame)
+            .min_by_key(|(_, (_, used))| *used)
+            .map(|(key, (slot, _))| (*key, *slot))?;
+
+        self.resident.remove(&key);
+
+        Some(slot)
+    }
+
+    // renders a tile into the scratch images, then into its slot and a host readback
+    fn compute(&mut self,
+            builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            memory_allocator: &StandardMemoryAllocator,
+            pipeline: Arc<ComputePipeline>,
+            slot: u32,
+            key: &TileKey) {
+        let push_constants = key.camera().push_constants(1, false, [0, 0]);
+
+        builder
+            .bind_pipeline_compute(pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                pipeline.layout().clone(),
+                0,
+                self.scratch_set.clone(),
+            )
+            .push_constants(pipeline.layout().clone(), 0, push_constants)
+            .dispatch([TILE_PIXELS / 8, TILE_PIXELS / 8, 1])
+            .unwrap();
+
+        let corner = self.slot_corner(slot);
+
+        for (scratch, atlas) in [(&self.scratch_samples, &self.atlas_samples), (&self.scratch_orbits, &self.atlas_orbits)] {
+            let mut info = CopyImageInfo::images(scratch.image().clone(), atlas.image().clone());
+            info.regions[0].dst_offset = [corner[0], corner[1], 0];
+            info.regions[0].extent = [TILE_PIXELS, TILE_PIXELS, 1];
+
+            builder.copy_image(info).unwrap();
+        }
+
+        let buffer = Buffer::new_slice::<u8>(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Download,
+                ..Default::default()
+            },
+            TILE_BYTES,
+        )
+        .unwrap();
+
+        builder
+            .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(
+                self.scratch_samples.image().clone(),
+                buffer.clone().slice(0..SAMPLE_BYTES),
+            ))
+            .unwrap()
+            .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(
+                self.scratch_orbits.image().clone(),
+                buffer.clone().slice(SAMPLE_BYTES..TILE_BYTES),
+            ))
+            .unwrap();
+
+        self.downloads.push((*key, buffer));
+    }
+
+    // copies a host copy into its slot
+    fn upload(&self,
+            builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            memory_allocator: &StandardMemoryAllocator,
+            slot: u32,
+            bytes: Vec<u8>) {
+        let buffer = Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            bytes,
+        )
+        .unwrap();
+
+        let corner = self.slot_corner(slot);
+
+        for (range, atlas) in [(0..SAMPLE_BYTES, &self.atlas_samples), (SAMPLE_BYTES..TILE_BYTES, &self.atlas_orbits)] {
+            let mut info = CopyBufferToImageInfo::buffer_image(buffer.clone().slice(range), atlas.image().clone());
+            info.regions[0].image_offset = [corner[0], corner[1], 0];
+            info.regions[0].image_extent = [TILE_PIXELS, TILE_PIXELS, 1];
+
+            builder.copy_buffer_to_image(info).unwrap();
+        }
+    }
+
+    // host copy of a tile from memory or the spill directory
+    fn host_copy(&mut self, key: &TileKey) -> Option<Vec<u8>> {
+        if let Some(bytes) = self.memory.get(key) {
+            return Some(bytes.clone());
+        }
+
+        let dir = self.spill.as_ref()?;
+        let name = key.file_name();
+        let bytes = fs::read(dir.join(&name)).ok()?;
+
+        // cut short by a crash
+        if bytes.len() as u64 != TILE_BYTES {
+            return None;
+        }
+
+        // most recently used now
+        self.spilled.retain(|spilled| *spilled != name);
+        self.spilled.push_back(name);
+
+        self.keep(*key, bytes.clone());
+
+        Some(bytes)
+    }
+
+    // takes in the readbacks the gpu is done with
+    fn collect_downloads(&mut self) {
+        let mut pending = Vec::new();
+
+        for (key, buffer) in std::mem::take(&mut self.downloads) {
+            let bytes = buffer.read().ok().map(|bytes| bytes.to_vec());
+
+            match bytes {
+                Some(bytes) => self.keep(key, bytes),
+                None => pending.push((key, buffer)),
+            }
+        }
+
+        self.downloads = pending;
+    }
+
+    fn keep(&mut self, key: TileKey, bytes: Vec<u8>) {
+        if self.memory.insert(key, bytes).is_none() {
+            self.memory_order.push_back(key);
+        }
+
+        while self.memory_order.len() > MEMORY_TILES {
+            let oldest = self.memory_order.pop_front().unwrap();
+            let bytes = self.memory.remove(&oldest).unwrap();
+
+            if let Some(dir) = &self.spill {
+                let name = oldest.file_name();
+
+                // read back from the directory earlier, the file is still there
+                let before = self.spilled.len();
+                self.spilled.retain(|spilled| *spilled != name);
+
+                if self.spilled.len() == before {
+                    if let Err(e) = fs::write(dir.join(&name), &bytes) {
+                        eprintln!("failed to spill cache tile: {}", e);
+                        continue;
+                    }
+                }
+
+                self.spilled.push_back(name);
+
+                while self.spilled.len() > SPILL_TILES {
+                    let _ = fs::remove_file(dir.join(self.spilled.pop_front().unwrap()));
+                }
+            }
+        }
+    }
+}
+
+// tiles already in the spill directory, least recently written first. tiles of another
+// `FORMAT_VERSION` are never read again and get deleted
+fn spilled_tiles(dir: &Path) -> VecDeque<String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return VecDeque::new(),
+    };
+
+    let prefix = format!("v{}_", FORMAT_VERSION);
+    let mut tiles = Vec::new();
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if !name.ends_with(".tile") {
+            continue;
+        }
+
+        if !name.starts_with(&prefix) {
+            let _ = fs::remove_file(entry.path());
+            continue;
+        }
+
+        let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok();
+        tiles.push((modified, name));
+    }
+
+    tiles.sort();
+
+    let mut tiles: VecDeque<String> = tiles.into_iter().map(|(_, name)| name).collect();
+
+    while tiles.len() > SPILL_TILES {
+        let _ = fs::remove_file(dir.join(tiles.pop_front().unwrap()));
+    }
+
+    tiles
+}
@@ -0,0 +1,87 @@
+// how the colorize pass turns the raw results into a color, the discriminant is what it
+// switches on
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Coloring {
+    // grayscale iteration count
+    Iterations = 0,
+    // thin lines along the boundary from the distance estimate, the same at any limit
+    Boundary = 1,
+    // brightness falling off with the distance estimate
+    Glow = 2,
+    // smooth iteration count with extra samples where the distance estimate is under a pixel
+    AntiAliased = 3,
+    // iteration count spread evenly over the palette by the frame's histogram, see
+    // `renderer::histogram`
+    Histogram = 4
+}
+
+impl Coloring {
+    pub fn next(self) -> Coloring {
+        match self {
+            Coloring::Iterations => Coloring::Boundary,
+            Coloring::Boundary => Coloring::Glow,
+            Coloring::Glow => Coloring::AntiAliased,
+            Coloring::AntiAliased => Coloring::Histogram,
+            Coloring::Histogram => Coloring::Iterations,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Coloring::Iterations => "iterations",
+            Coloring::Boundary => "boundary",
+            Coloring::Glow => "glow",
+            Coloring::AntiAliased => "anti aliased",
+            Coloring::Histogram => "histogram",
+        }
+    }
+
+    // the only coloring that changes how the samples are taken rather than just how
+    // they're colored, switching to or from it renders the view again
+    pub fn supersamples(self) -> bool {
+        self == Coloring::AntiAliased
+    }
+
+    // whether the color outside the set follows from the integer iteration count alone,
+    // which is what lets subdivision fill in rectangles
+    pub fn by_count(self) -> bool {
+        matches!(self, Coloring::Iterations | Coloring::Histogram)
+    }
+}
+
+// how points that never escape are colored, on top of any `Coloring`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Interior {
+    // whatever the coloring gives the iteration limit
+    Flat = 0,
+    // length of the attracting cycle the periodicity check found
+    Period = 1,
+    // |multiplier| of that cycle, dark at the centers of the components
+    Multiplier = 2,
+    // interior distance estimate to the boundary
+    Distance = 3,
+    // angle of the last z
+    Angle = 4
+}
+
+impl Interior {
+    pub fn next(self) -> Interior {
+        match self {
+            Interior::Flat => Interior::Period,
+            Interior::Period => Interior::Multiplier,
+            Interior::Multiplier => Interior::Distance,
+            Interior::Distance => Interior::Angle,
+            Interior::Angle => Interior::Flat,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Interior::Flat => "flat",
+            Interior::Period => "period",
+            Interior::Multiplier => "multiplier",
+            Interior::Distance => "distance",
+            Interior::Angle => "angle",
+        }
+    }
+}
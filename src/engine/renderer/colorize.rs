@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
+
+use super::render::RenderCamera;
+
+use crate::engine::shaders::mandelbrot;
+
+// records coloring raw results of the camera's view into an image of the given size, the
+// set comes from `frame::colorize_set`. resume_from is the one the samples are being resumed
+// from, 0 if they aren't, see `mandelbrot::cs`
+pub fn record(builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        pipeline: Arc<ComputePipeline>,
+        set: Arc<PersistentDescriptorSet>,
+        camera: &RenderCamera,
+        dims: [u32; 2],
+        resume_from: u32) {
+    let push_constants = mandelbrot::colorize::PushConstants {
+        max_iters: camera.max_iters,
+        coloring: camera.coloring as u32,
+        interior: camera.interior as u32,
+        pixel: camera.pixel_size(dims[1] as f32),
+        resume_from,
+    };
+
+    builder
+        .bind_pipeline_compute(pipeline.clone())
+        .bind_descriptor_sets(
+            PipelineBindPoint::Compute,
+            pipeline.layout().clone(),
+            0,
+            set,
+        )
+        .push_constants(pipeline.layout().clone(), 0, push_constants)
+        .dispatch([(dims[0] + 7) / 8, (dims[1] + 7) / 8, 1])
+        .unwrap();
+}
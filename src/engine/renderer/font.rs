@@ -0,0 +1,167 @@
+// 8x12 bitmap font covering printable ascii (' ' through '~'), baked from
+// DejaVu Sans Mono. each entry is one glyph, one byte per row, msb = leftmost pixel
+pub const GLYPH_WIDTH: u32 = 8;
+pub const GLYPH_HEIGHT: u32 = 12;
+
+pub const FIRST_CHAR: u8 = b' ';
+pub const LAST_CHAR: u8 = b'~';
+
+// atlas layout, 16 glyphs per row. the slot after '~' is left solid so
+// the hud can draw untextured boxes out of the same atlas
+pub const ATLAS_COLUMNS: u32 = 16;
+pub const ATLAS_ROWS: u32 = 6;
+pub const SOLID_GLYPH: u32 = (LAST_CHAR - FIRST_CHAR) as u32 + 1;
+
+pub const ATLAS_WIDTH: u32 = ATLAS_COLUMNS * GLYPH_WIDTH;
+pub const ATLAS_HEIGHT: u32 = ATLAS_ROWS * GLYPH_HEIGHT;
+
+const GLYPHS: [[u8; GLYPH_HEIGHT as usize]; (LAST_CHAR - FIRST_CHAR) as usize + 1] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00], // '!'
+    [0x00, 0x28, 0x28, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x00, 0x14, 0x2c, 0x7c, 0x68, 0x28, 0xfc, 0x50, 0x50, 0x00, 0x00, 0x00], // '#'
+    [0x00, 0x10, 0x38, 0x50, 0x70, 0x38, 0x1c, 0x14, 0x78, 0x10, 0x00, 0x00], // '$'
+    [0x00, 0x40, 0xe0, 0xb0, 0x6c, 0x70, 0x1c, 0x14, 0x1c, 0x00, 0x00, 0x00], // '%'
+    [0x00, 0x38, 0x40, 0x60, 0x60, 0xd4, 0x9c, 0xcc, 0x7c, 0x00, 0x00, 0x00], // '&'
+    [0x00, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '\''
+    [0x00, 0x18, 0x10, 0x30, 0x30, 0x20, 0x30, 0x30, 0x10, 0x10, 0x00, 0x00], // '('
+    [0x00, 0x20, 0x30, 0x10, 0x10, 0x10, 0x10, 0x10, 0x30, 0x20, 0x00, 0x00], // ')'
+    [0x00, 0x10, 0x58, 0x30, 0x58, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '*'
+    [0x00, 0x00, 0x00, 0x10, 0x10, 0xfc, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x30, 0x30, 0x20, 0x00], // ','
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x30, 0x30, 0x00, 0x00, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x30, 0x00, 0x00, 0x00], // '.'
+    [0x00, 0x0c, 0x08, 0x18, 0x10, 0x30, 0x20, 0x20, 0x40, 0x40, 0x00, 0x00], // '/'
+    [0x00, 0x38, 0x48, 0x4c, 0x5c, 0x54, 0x4c, 0x4c, 0x38, 0x00, 0x00, 0x00], // '0'
+    [0x00, 0x70, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00], // '1'
+    [0x00, 0x78, 0x08, 0x0c, 0x08, 0x18, 0x30, 0x60, 0x7c, 0x00, 0x00, 0x00], // '2'
+    [0x00, 0x78, 0x08, 0x0c, 0x38, 0x18, 0x0c, 0x0c, 0x78, 0x00, 0x00, 0x00], // '3'
+    [0x00, 0x18, 0x18, 0x28, 0x68, 0x48, 0xfc, 0x08, 0x08, 0x00, 0x00, 0x00], // '4'
+    [0x00, 0x78, 0x40, 0x40, 0x78, 0x0c, 0x0c, 0x0c, 0x78, 0x00, 0x00, 0x00], // '5'
+    [0x00, 0x38, 0x60, 0x40, 0x78, 0x4c, 0x4c, 0x4c, 0x38, 0x00, 0x00, 0x00], // '6'
+    [0x00, 0x7c, 0x08, 0x08, 0x18, 0x10, 0x10, 0x30, 0x20, 0x00, 0x00, 0x00], // '7'
+    [0x00, 0x78, 0x4c, 0x4c, 0x38, 0x78, 0x4c, 0x4c, 0x78, 0x00, 0x00, 0x00], // '8'
+    [0x00, 0x78, 0x48, 0x4c, 0x4c, 0x7c, 0x0c, 0x08, 0x78, 0x00, 0x00, 0x00], // '9'
+    [0x00, 0x00, 0x00, 0x10, 0x30, 0x00, 0x00, 0x10, 0x30, 0x00, 0x00, 0x00], // ':'
+    [0x00, 0x00, 0x00, 0x10, 0x30, 0x00, 0x00, 0x10, 0x30, 0x30, 0x20, 0x00], // ';'
+    [0x00, 0x00, 0x00, 0x0c, 0x38, 0xc0, 0x70, 0x0c, 0x00, 0x00, 0x00, 0x00], // '<'
+    [0x00, 0x00, 0x00, 0x00, 0xfc, 0x00, 0xfc, 0x00, 0x00, 0x00, 0x00, 0x00], // '='
+    [0x00, 0x00, 0x00, 0xc0, 0x78, 0x0c, 0x38, 0xe0, 0x00, 0x00, 0x00, 0x00], // '>'
+    [0x00, 0x78, 0x0c, 0x08, 0x18, 0x10, 0x30, 0x00, 0x30, 0x00, 0x00, 0x00], // '?'
+    [0x00, 0x00, 0x7c, 0x44, 0x9c, 0xa4, 0xa4, 0xbc, 0x40, 0x60, 0x18, 0x00], // '@'
+    [0x00, 0x30, 0x30, 0x38, 0x68, 0x48, 0x7c, 0xc4, 0xc4, 0x00, 0x00, 0x00], // 'A'
+    [0x00, 0x78, 0x4c, 0x4c, 0x78, 0x4c, 0x44, 0x4c, 0x78, 0x00, 0x00, 0x00], // 'B'
+    [0x00, 0x3c, 0x60, 0x40, 0x40, 0x40, 0x40, 0x60, 0x3c, 0x00, 0x00, 0x00], // 'C'
+    [0x00, 0x70, 0x48, 0x4c, 0x4c, 0x4c, 0x4c, 0x48, 0x78, 0x00, 0x00, 0x00], // 'D'
+    [0x00, 0x7c, 0x40, 0x40, 0x78, 0x60, 0x40, 0x40, 0x7c, 0x00, 0x00, 0x00], // 'E'
+    [0x00, 0x7c, 0x60, 0x40, 0x78, 0x60, 0x40, 0x40, 0x40, 0x00, 0x00, 0x00], // 'F'
+    [0x00, 0x38, 0x60, 0x40, 0xc0, 0xcc, 0x44, 0x44, 0x3c, 0x00, 0x00, 0x00], // 'G'
+    [0x00, 0x44, 0x44, 0x44, 0x7c, 0x4c, 0x44, 0x44, 0x44, 0x00, 0x00, 0x00], // 'H'
+    [0x00, 0x7c, 0x30, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00], // 'I'
+    [0x00, 0x38, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x78, 0x00, 0x00, 0x00], // 'J'
+    [0x00, 0x44, 0x48, 0x50, 0x70, 0x70, 0x58, 0x4c, 0x44, 0x00, 0x00, 0x00], // 'K'
+    [0x00, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x7c, 0x00, 0x00, 0x00], // 'L'
+    [0x00, 0xcc, 0xcc, 0xec, 0xf4, 0xf4, 0xc4, 0xc4, 0xc4, 0x00, 0x00, 0x00], // 'M'
+    [0x00, 0x44, 0x64, 0x64, 0x74, 0x54, 0x5c, 0x4c, 0x4c, 0x00, 0x00, 0x00], // 'N'
+    [0x00, 0x38, 0x4c, 0x4c, 0xc4, 0xc4, 0x4c, 0x4c, 0x78, 0x00, 0x00, 0x00], // 'O'
+    [0x00, 0x78, 0x4c, 0x44, 0x4c, 0x78, 0x40, 0x40, 0x40, 0x00, 0x00, 0x00], // 'P'
+    [0x00, 0x38, 0x4c, 0x4c, 0xc4, 0xc4, 0x4c, 0x4c, 0x78, 0x08, 0x00, 0x00], // 'Q'
+    [0x00, 0x78, 0x4c, 0x4c, 0x58, 0x78, 0x48, 0x4c, 0x44, 0x00, 0x00, 0x00], // 'R'
+    [0x00, 0x38, 0x40, 0x40, 0x70, 0x18, 0x0c, 0x0c, 0x78, 0x00, 0x00, 0x00], // 'S'
+    [0x00, 0xfc, 0x30, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00], // 'T'
+    [0x00, 0x44, 0x4c, 0x4c, 0x4c, 0x4c, 0x4c, 0x4c, 0x78, 0x00, 0x00, 0x00], // 'U'
+    [0x00, 0xc4, 0x44, 0x4c, 0x48, 0x68, 0x38, 0x30, 0x30, 0x00, 0x00, 0x00], // 'V'
+    [0x00, 0x86, 0x84, 0x94, 0xf4, 0xf4, 0x6c, 0x6c, 0x4c, 0x00, 0x00, 0x00], // 'W'
+    [0x00, 0x44, 0x6c, 0x38, 0x30, 0x30, 0x28, 0x4c, 0xc4, 0x00, 0x00, 0x00], // 'X'
+    [0x00, 0xc4, 0x4c, 0x68, 0x38, 0x30, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00], // 'Y'
+    [0x00, 0x7c, 0x0c, 0x08, 0x18, 0x30, 0x20, 0x60, 0x7c, 0x00, 0x00, 0x00], // 'Z'
+    [0x00, 0x38, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x10, 0x00], // '['
+    [0x00, 0x40, 0x40, 0x60, 0x20, 0x30, 0x10, 0x18, 0x08, 0x0c, 0x00, 0x00], // '\\'
+    [0x00, 0x30, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x30, 0x30, 0x00], // ']'
+    [0x00, 0x30, 0x38, 0x4c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfc], // '_'
+    [0x20, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x00, 0x78, 0x0c, 0x3c, 0x4c, 0x4c, 0x7c, 0x00, 0x00, 0x00], // 'a'
+    [0x00, 0x40, 0x40, 0x78, 0x6c, 0x44, 0x44, 0x4c, 0x78, 0x00, 0x00, 0x00], // 'b'
+    [0x00, 0x00, 0x00, 0x3c, 0x60, 0x40, 0x40, 0x60, 0x3c, 0x00, 0x00, 0x00], // 'c'
+    [0x00, 0x0c, 0x0c, 0x7c, 0x4c, 0x4c, 0x4c, 0x4c, 0x7c, 0x00, 0x00, 0x00], // 'd'
+    [0x00, 0x00, 0x00, 0x38, 0x4c, 0x7c, 0x40, 0x40, 0x7c, 0x00, 0x00, 0x00], // 'e'
+    [0x00, 0x1c, 0x30, 0x7c, 0x30, 0x30, 0x30, 0x30, 0x30, 0x00, 0x00, 0x00], // 'f'
+    [0x00, 0x00, 0x00, 0x7c, 0x4c, 0x4c, 0x4c, 0x4c, 0x7c, 0x0c, 0x78, 0x00], // 'g'
+    [0x00, 0x40, 0x40, 0x78, 0x6c, 0x4c, 0x4c, 0x4c, 0x4c, 0x00, 0x00, 0x00], // 'h'
+    [0x00, 0x10, 0x00, 0x70, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00], // 'i'
+    [0x00, 0x10, 0x00, 0x70, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x70, 0x00], // 'j'
+    [0x00, 0x40, 0x40, 0x4c, 0x58, 0x70, 0x78, 0x48, 0x44, 0x00, 0x00, 0x00], // 'k'
+    [0x00, 0x70, 0x30, 0x30, 0x30, 0x30, 0x30, 0x30, 0x1c, 0x00, 0x00, 0x00], // 'l'
+    [0x00, 0x00, 0x00, 0xfc, 0xd4, 0xd4, 0xd4, 0xd4, 0xd4, 0x00, 0x00, 0x00], // 'm'
+    [0x00, 0x00, 0x00, 0x78, 0x6c, 0x4c, 0x4c, 0x4c, 0x4c, 0x00, 0x00, 0x00], // 'n'
+    [0x00, 0x00, 0x00, 0x38, 0x4c, 0x4c, 0x44, 0x4c, 0x78, 0x00, 0x00, 0x00], // 'o'
+    [0x00, 0x00, 0x00, 0x78, 0x4c, 0x44, 0x44, 0x4c, 0x78, 0x40, 0x40, 0x00], // 'p'
+    [0x00, 0x00, 0x00, 0x3c, 0x4c, 0x4c, 0x4c, 0x4c, 0x7c, 0x0c, 0x0c, 0x00], // 'q'
+    [0x00, 0x00, 0x00, 0x3c, 0x30, 0x20, 0x20, 0x20, 0x20, 0x00, 0x00, 0x00], // 'r'
+    [0x00, 0x00, 0x00, 0x38, 0x40, 0x70, 0x18, 0x0c, 0x78, 0x00, 0x00, 0x00], // 's'
+    [0x00, 0x20, 0x20, 0x78, 0x20, 0x20, 0x20, 0x30, 0x18, 0x00, 0x00, 0x00], // 't'
+    [0x00, 0x00, 0x00, 0x4c, 0x4c, 0x4c, 0x4c, 0x4c, 0x7c, 0x00, 0x00, 0x00], // 'u'
+    [0x00, 0x00, 0x00, 0x44, 0x4c, 0x68, 0x28, 0x38, 0x30, 0x00, 0x00, 0x00], // 'v'
+    [0x00, 0x00, 0x00, 0x84, 0x84, 0xf4, 0x74, 0x6c, 0x68, 0x00, 0x00, 0x00], // 'w'
+    [0x00, 0x00, 0x00, 0x4c, 0x68, 0x30, 0x30, 0x68, 0x4c, 0x00, 0x00, 0x00], // 'x'
+    [0x00, 0x00, 0x00, 0x44, 0x4c, 0x68, 0x28, 0x38, 0x30, 0x30, 0x60, 0x00], // 'y'
+    [0x00, 0x00, 0x00, 0x7c, 0x08, 0x10, 0x30, 0x60, 0x7c, 0x00, 0x00, 0x00], // 'z'
+    [0x00, 0x18, 0x10, 0x10, 0x30, 0x60, 0x30, 0x10, 0x10, 0x10, 0x18, 0x00], // '{'
+    [0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10], // '|'
+    [0x00, 0x70, 0x30, 0x10, 0x10, 0x18, 0x10, 0x10, 0x10, 0x30, 0x60, 0x00], // '}'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0xfc, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+];
+
+// glyph slot for a character, unknown characters become '?'
+pub fn glyph_index(c: char) -> u32 {
+    if c.is_ascii() && (c as u8) >= FIRST_CHAR && (c as u8) <= LAST_CHAR {
+        return (c as u8 - FIRST_CHAR) as u32;
+    }
+
+    return (b'?' - FIRST_CHAR) as u32;
+}
+
+// uv rectangle (min, max) of a glyph slot in the atlas
+pub fn glyph_uv(index: u32) -> ([f32; 2], [f32; 2]) {
+    let col = index % ATLAS_COLUMNS;
+    let row = index / ATLAS_COLUMNS;
+
+    let min = [
+        (col * GLYPH_WIDTH) as f32 / ATLAS_WIDTH as f32,
+        (row * GLYPH_HEIGHT) as f32 / ATLAS_HEIGHT as f32,
+    ];
+    let max = [
+        ((col + 1) * GLYPH_WIDTH) as f32 / ATLAS_WIDTH as f32,
+        ((row + 1) * GLYPH_HEIGHT) as f32 / ATLAS_HEIGHT as f32,
+    ];
+
+    (min, max)
+}
+
+// expands the glyph bitmaps into a single channel atlas, one byte per texel
+pub fn atlas() -> Vec<u8> {
+    let mut pixels = vec![0u8; (ATLAS_WIDTH * ATLAS_HEIGHT) as usize];
+
+    for slot in 0..ATLAS_COLUMNS * ATLAS_ROWS {
+        let rows = match GLYPHS.get(slot as usize) {
+            Some(glyph) => *glyph,
+            None if slot == SOLID_GLYPH => [0xff; GLYPH_HEIGHT as usize],
+            None => continue,
+        };
+
+        let origin_x = (slot % ATLAS_COLUMNS) * GLYPH_WIDTH;
+        let origin_y = (slot / ATLAS_COLUMNS) * GLYPH_HEIGHT;
+
+        for (y, bits) in rows.iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                if bits & (0x80 >> x) != 0 {
+                    let i = (origin_y + y as u32) * ATLAS_WIDTH + origin_x + x;
+                    pixels[i as usize] = 0xff;
+                }
+            }
+        }
+    }
+
+    pixels
+}
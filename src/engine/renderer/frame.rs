@@ -0,0 +1,258 @@
+use std::sync::Arc;
+
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::image::{ImageAccess, ImageCreateFlags, ImageDimensions, ImageUsage, StorageImage, SwapchainImage};
+use vulkano::image::view::ImageView;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::{ComputePipeline, GraphicsPipeline, Pipeline};
+use vulkano::query::{QueryPool, QueryPoolCreateInfo, QueryType};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass};
+use vulkano::sampler::Sampler;
+
+// the raw results, their copies from the last frame they're reprojected from, the colored
+// render target and the descriptor sets using them. rebuilt when the swapchain changes size
+// and kept otherwise. only the target is shared between the compute and graphics queue
+// families, see `new_image`; the raw results never leave the compute family.
+//
+// samples holds iterations, smooth iterations, distance estimate and the exact flag, orbits
+// the last z, period and multiplier. the compute set also has where every exact sample's
+// orbit stopped, so a higher limit can carry on from there. see `mandelbrot::cs` and
+// `mandelbrot::colorize`
+pub struct TargetSets {
+    pub target: Arc<ImageView<StorageImage>>,
+    pub samples: Arc<ImageView<StorageImage>>,
+    pub orbits: Arc<ImageView<StorageImage>>,
+    pub previous_samples: Arc<ImageView<StorageImage>>,
+    pub previous_orbits: Arc<ImageView<StorageImage>>,
+
+    pub compute: Arc<PersistentDescriptorSet>,
+    pub colorize: Arc<PersistentDescriptorSet>,
+    pub graphics: Arc<PersistentDescriptorSet>,
+    pub reproject: Arc<PersistentDescriptorSet>,
+
+    // whether there's an orbit state for every pixel, a raised limit recomputes everything
+    // when they don't fit into one storage buffer
+    pub resumable: bool
+}
+
+impl TargetSets {
+    pub fn new(memory_allocator: &StandardMemoryAllocator,
+            descriptor_set_allocator: &StandardDescriptorSetAllocator,
+            dims: [u32; 2],
+            queue_family_indices: &[u32],
+            compute_family: u32,
+            max_storage_buffer_range: u32,
+            sampler: Arc<Sampler>,
+            cdf: Subbuffer<[f32]>,
+            compute_pipeline: &ComputePipeline,
+            reproject_pipeline: &ComputePipeline,
+            colorize_pipeline: &ComputePipeline,
+            graphics_pipeline: &GraphicsPipeline) -> TargetSets {
+        let target = new_image(
+            memory_allocator,
+            dims,
+            Format::R8G8B8A8_UNORM,
+            ImageUsage::STORAGE | ImageUsage::SAMPLED | ImageUsage::TRANSFER_SRC,
+            queue_family_indices,
+        );
+
+        let raw_usage = ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC | ImageUsage::TRANSFER_DST;
+
+        let samples = new_image(memory_allocator, dims, SAMPLES_FORMAT, raw_usage, &[compute_family]);
+        let orbits = new_image(memory_allocator, dims, ORBITS_FORMAT, raw_usage, &[compute_family]);
+        let previous_samples = new_image(memory_allocator, dims, SAMPLES_FORMAT, raw_usage, &[compute_family]);
+        let previous_orbits = new_image(memory_allocator, dims, ORBITS_FORMAT, raw_usage, &[compute_family]);
+
+        let pixels = dims[0] as u64 * dims[1] as u64;
+        let resumable = pixels * STATE_WORDS * 4 <= max_storage_buffer_range as u64;
+
+        let states = new_states(memory_allocator, if resumable { pixels } else { 1 });
+
+        let compute = raw_set(descriptor_set_allocator, compute_pipeline, &samples, &orbits, states);
+        let colorize = colorize_set(descriptor_set_allocator, colorize_pipeline, &samples, &orbits, &target, cdf);
+
+        let graphics = PersistentDescriptorSet::new(
+            descriptor_set_allocator,
+            graphics_pipeline.layout().set_layouts().get(0).unwrap().clone(),
+            [WriteDescriptorSet::image_view_sampler(0, target.clone(), sampler)],
+        )
+        .unwrap();
+
+        let reproject = PersistentDescriptorSet::new(
+            descriptor_set_allocator,
+            reproject_pipeline.layout().set_layouts().get(0).unwrap().clone(),
+            [
+                WriteDescriptorSet::image_view(0, previous_samples.clone()),
+                WriteDescriptorSet::image_view(1, previous_orbits.clone()),
+                WriteDescriptorSet::image_view(2, samples.clone()),
+                WriteDescriptorSet::image_view(3, orbits.clone()),
+            ],
+        )
+        .unwrap();
+
+        TargetSets {
+            target,
+            samples,
+            orbits,
+            previous_samples,
+            previous_orbits,
+
+            compute,
+            colorize,
+            graphics,
+            reproject,
+
+            resumable
+        }
+    }
+
+    pub fn dims(&self) -> [u32; 2] {
+        self.target.image().dimensions().width_height()
+    }
+}
+
+pub const SAMPLES_FORMAT: Format = Format::R32G32B32A32_SFLOAT;
+// half floats are plenty for an angle, a small period and a multiplier in 0..1
+pub const ORBITS_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+// u32s of an `OrbitState`, must match the kernel
+const STATE_WORDS: u64 = 12;
+
+// orbit states for this many pixels, see `mandelbrot::cs`. renders that never resume
+// pass 1, the kernel only keeps states when there's one for every pixel
+pub fn new_states(memory_allocator: &StandardMemoryAllocator, pixels: u64) -> Subbuffer<[u32]> {
+    Buffer::new_slice::<u32>(
+        memory_allocator,
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            usage: MemoryUsage::DeviceOnly,
+            ..Default::default()
+        },
+        pixels * STATE_WORDS,
+    )
+    .unwrap()
+}
+
+// set of the `cs` kernel, samples at 0, orbits at 1 and the orbit states at 2
+pub fn raw_set(descriptor_set_allocator: &StandardDescriptorSetAllocator,
+        pipeline: &ComputePipeline,
+        samples: &Arc<ImageView<StorageImage>>,
+        orbits: &Arc<ImageView<StorageImage>>,
+        states: Subbuffer<[u32]>) -> Arc<PersistentDescriptorSet> {
+    PersistentDescriptorSet::new(
+        descriptor_set_allocator,
+        pipeline.layout().set_layouts().get(0).unwrap().clone(),
+        [
+            WriteDescriptorSet::image_view(0, samples.clone()),
+            WriteDescriptorSet::image_view(1, orbits.clone()),
+            WriteDescriptorSet::buffer(2, states),
+        ],
+    )
+    .unwrap()
+}
+
+// set of the `colorize` pass, the cdf is only read by the histogram coloring but always bound
+pub fn colorize_set(descriptor_set_allocator: &StandardDescriptorSetAllocator,
+        pipeline: &ComputePipeline,
+        samples: &Arc<ImageView<StorageImage>>,
+        orbits: &Arc<ImageView<StorageImage>>,
+        target: &Arc<ImageView<StorageImage>>,
+        cdf: Subbuffer<[f32]>) -> Arc<PersistentDescriptorSet> {
+    PersistentDescriptorSet::new(
+        descriptor_set_allocator,
+        pipeline.layout().set_layouts().get(0).unwrap().clone(),
+        [
+            WriteDescriptorSet::image_view(0, samples.clone()),
+            WriteDescriptorSet::image_view(1, orbits.clone()),
+            WriteDescriptorSet::image_view(2, target.clone()),
+            WriteDescriptorSet::buffer(3, cdf),
+        ],
+    )
+    .unwrap()
+}
+
+// one family makes the image exclusive to it, more than one concurrently shared between them.
+// only images the graphics pass samples get both families: vulkano's command buffer builder
+// puts its own barriers in and has no way to record the release/acquire pair an exclusive
+// image needs to change families, so the concurrent sharing stands in for the ownership
+// transfer. that's the colored target, the julia inset and the minimap, each written once
+// by the colorize pass and read once by a draw per frame, so losing the exclusive layout
+// optimizations on them costs next to nothing. on devices with one family for both queues
+// everything is exclusive as before
+pub fn new_image(memory_allocator: &StandardMemoryAllocator,
+        dims: [u32; 2],
+        format: Format,
+        usage: ImageUsage,
+        queue_family_indices: &[u32]) -> Arc<ImageView<StorageImage>> {
+    let image = StorageImage::with_usage(
+        memory_allocator,
+        ImageDimensions::Dim2d {
+            width: dims[0],
+            height: dims[1],
+            array_layers: 1,
+        },
+        format,
+        usage,
+        ImageCreateFlags::empty(),
+        queue_family_indices.iter().copied(),
+    )
+    .unwrap();
+
+    ImageView::new_default(image).unwrap()
+}
+
+// everything tied to one swapchain image, rebuilt when the swapchain is
+pub struct FrameSlot {
+    pub image: Arc<ImageView<SwapchainImage>>,
+    pub framebuffer: Arc<Framebuffer>,
+
+    // gpu timestamps around the tile batch of the last frame that used this slot,
+    // none if the queue can't write them
+    pub timestamps: Option<Arc<QueryPool>>,
+    // whether that frame had a batch to time
+    pub timed: bool
+}
+
+impl FrameSlot {
+    pub fn new(device: Arc<Device>,
+            render_pass: Arc<RenderPass>,
+            image: Arc<ImageView<SwapchainImage>>,
+            can_time: bool) -> FrameSlot {
+        let framebuffer = Framebuffer::new(
+            render_pass,
+            FramebufferCreateInfo {
+                attachments: vec![image.clone()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let timestamps = if can_time {
+            Some(QueryPool::new(
+                device,
+                QueryPoolCreateInfo {
+                    query_count: 2,
+                    ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+                },
+            )
+            .unwrap())
+        } else {
+            None
+        };
+
+        FrameSlot {
+            image,
+            framebuffer,
+
+            timestamps,
+            timed: false
+        }
+    }
+}
@@ -1,41 +1,58 @@
-use std::{ops::Range, sync::Arc};
+use std::sync::Arc;
 
-use vulkano::device::{physical::PhysicalDevice, Device};
-use vulkano::swapchain::{Surface, Swapchain, SwapchainCreateInfo};
-use vulkano::image::{SwapchainImage, ImageUsage};
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
 
-use winit::window::Window;
+use super::render::Vertex;
 
-pub fn new_swapchain(p_device: Arc<PhysicalDevice>, 
-        window: Arc<Window>,
-        surface: Arc<Surface>,
-        device: Arc<Device>) -> (Arc<Swapchain>, Vec<Arc<SwapchainImage>>){
-    let caps = p_device
-    .surface_capabilities(&surface, Default::default())
-    .expect("failed to get surface capabilities");
+// draws a sampled image into a window pixel rectangle with the fullscreen graphics pipeline,
+// the render pass has to be started already
+pub fn draw_image_quad(builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        memory_allocator: &StandardMemoryAllocator,
+        graphics_pipeline: Arc<GraphicsPipeline>,
+        set: Arc<PersistentDescriptorSet>,
+        min: [f32; 2],
+        max: [f32; 2],
+        window_size: [f32; 2]) {
+    let to_ndc = |p: [f32; 2]| [p[0] / window_size[0] * 2.0 - 1.0, p[1] / window_size[1] * 2.0 - 1.0];
 
-    let dimensions = window.inner_size();
-    let composite_alpha = caps.supported_composite_alpha.into_iter().next().unwrap();
-    let image_format = Some(
-    p_device
-        .surface_formats(&surface, Default::default())
-        .unwrap()[0]
-        .0,
-    );
+    let vertex = |p: [f32; 2], tex_coords: [f32; 2]| Vertex { position: to_ndc(p), tex_coords };
 
-    let (swapchain, images) = Swapchain::new(
-        device.clone(),
-        surface.clone(),
-        SwapchainCreateInfo {
-            min_image_count: caps.min_image_count + 1, // How many buffers to use in the swapchain
-            image_format,
-            image_extent: dimensions.into(),
-            image_usage: ImageUsage::COLOR_ATTACHMENT, // What the images are going to be used for
-            composite_alpha,
+    let vertices = [
+        vertex([min[0], min[1]], [0.0, 0.0]),
+        vertex([max[0], min[1]], [1.0, 0.0]),
+        vertex([min[0], max[1]], [0.0, 1.0]),
+        vertex([min[0], max[1]], [0.0, 1.0]),
+        vertex([max[0], min[1]], [1.0, 0.0]),
+        vertex([max[0], max[1]], [1.0, 1.0]),
+    ];
+
+    let vertex_buffer = Buffer::from_iter(
+        memory_allocator,
+        BufferCreateInfo {
+            usage: BufferUsage::VERTEX_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            usage: MemoryUsage::Upload,
             ..Default::default()
         },
+        vertices,
     )
     .unwrap();
 
-    (swapchain, images)
-}
\ No newline at end of file
+    builder
+        .bind_pipeline_graphics(graphics_pipeline.clone())
+        .bind_descriptor_sets(
+            PipelineBindPoint::Graphics,
+            graphics_pipeline.layout().clone(),
+            0,
+            set,
+        )
+        .bind_vertex_buffers(0, vertex_buffer)
+        .draw(6, 1, 0, 0)
+        .unwrap();
+}
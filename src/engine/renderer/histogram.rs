@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, FillBufferInfo, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::Device;
+use vulkano::image::{ImageAccess, StorageImage};
+use vulkano::image::view::ImageView;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
+
+use crate::engine::shaders::histogram;
+
+// bins over iterations / max_iters, enough that neighbouring counts rarely share one
+pub const BINS: usize = 4096;
+
+// the `Histogram` coloring. every frame the samples are binned by iteration count and the
+// bins are summed into a cdf, which the colorize pass spreads over the palette so it covers
+// whatever range of counts is on screen
+pub struct Histogram {
+    count_pipeline: Arc<ComputePipeline>,
+    scan_pipeline: Arc<ComputePipeline>,
+
+    bins: Subbuffer<[u32]>,
+    // bound to every colorize set. exclusive like bins, only the compute queue ever
+    // touches either
+    cdf: Subbuffer<[f32]>,
+    scan_set: Arc<PersistentDescriptorSet>,
+
+    // counting set for the samples image last binned
+    count_set: Option<(Arc<ImageView<StorageImage>>, Arc<PersistentDescriptorSet>)>
+}
+
+impl Histogram {
+    pub fn new(device: Arc<Device>,
+            memory_allocator: &StandardMemoryAllocator,
+            descriptor_set_allocator: &StandardDescriptorSetAllocator) -> Histogram {
+        let count_shader = histogram::count::load(device.clone()).unwrap();
+        let scan_shader = histogram::scan::load(device.clone()).unwrap();
+
+        let count_pipeline = ComputePipeline::new(
+            device.clone(),
+            count_shader.entry_point("main").unwrap(),
+            &(),
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        let scan_pipeline = ComputePipeline::new(
+            device.clone(),
+            scan_shader.entry_point("main").unwrap(),
+            &(),
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        let bins = Buffer::new_slice::<u32>(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::TRANSFER_DST,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::DeviceOnly,
+                ..Default::default()
+            },
+            BINS as u64,
+        )
+        .unwrap();
+
+        let cdf = Buffer::new_slice::<f32>(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::DeviceOnly,
+                ..Default::default()
+            },
+            BINS as u64,
+        )
+        .unwrap();
+
+        let scan_set = PersistentDescriptorSet::new(
+            descriptor_set_allocator,
+            scan_pipeline.layout().set_layouts().get(0).unwrap().clone(),
+            [
+                WriteDescriptorSet::buffer(0, bins.clone()),
+                WriteDescriptorSet::buffer(1, cdf.clone()),
+            ],
+        )
+        .unwrap();
+
+        Histogram {
+            count_pipeline,
+            scan_pipeline,
+
+            bins,
+            cdf,
+            scan_set,
+
+            count_set: None
+        }
+    }
+
+    pub fn cdf(&self) -> Subbuffer<[f32]> {
+        self.cdf.clone()
+    }
+
+    // records binning the samples and building the cdf the colorize pass reads
+    pub fn record(&mut self,
+            builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            descriptor_set_allocator: &StandardDescriptorSetAllocator,
+            samples: Arc<ImageView<StorageImage>>,
+            max_iters: u32) {
+        let stale = match &self.count_set {
+            Some((current, _)) => !Arc::ptr_eq(current, &samples),
+            None => true,
+        };
+
+        if stale {
+            let count_set = PersistentDescriptorSet::new(
+                descriptor_set_allocator,
+                self.count_pipeline.layout().set_layouts().get(0).unwrap().clone(),
+                [
+                    WriteDescriptorSet::image_view(0, samples.clone()),
+                    WriteDescriptorSet::buffer(1, self.bins.clone()),
+                ],
+            )
+            .unwrap();
+
+            self.count_set = Some((samples.clone(), count_set));
+        }
+
+        let (_, count_set) = self.count_set.as_ref().unwrap();
+        let [width, height] = samples.image().dimensions().width_height();
+
+        builder
+            .fill_buffer(FillBufferInfo {
+                data: 0,
+                ..FillBufferInfo::dst_buffer(self.bins.clone())
+            })
+            .unwrap()
+            .bind_pipeline_compute(self.count_pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.count_pipeline.layout().clone(),
+                0,
+                count_set.clone(),
+            )
+            .push_constants(self.count_pipeline.layout().clone(), 0, histogram::count::PushConstants { max_iters })
+            .dispatch([(width + 7) / 8, (height + 7) / 8, 1])
+            .unwrap()
+            .bind_pipeline_compute(self.scan_pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.scan_pipeline.layout().clone(),
+                0,
+                self.scan_set.clone(),
+            )
+            .dispatch([1, 1, 1])
+            .unwrap();
+    }
+}
@@ -0,0 +1,428 @@
+use std::sync::Arc;
+
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, BufferContents};
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, PrimaryCommandBufferAbstract};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::image::{ImageDimensions, ImmutableImage, MipmapsCount};
+use vulkano::image::view::ImageView;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::graphics::color_blend::ColorBlendState;
+use vulkano::pipeline::graphics::vertex_input::Vertex as VertexTrait;
+use vulkano::pipeline::graphics::viewport::ViewportState;
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::render_pass::Subpass;
+use vulkano::sampler::{Filter, Sampler, SamplerCreateInfo};
+use vulkano::sync::GpuFuture;
+
+use super::font;
+use super::minimap::Marker;
+use super::render::RenderCamera;
+
+use crate::engine::orbit::Orbit;
+
+use crate::engine::shaders::overlay;
+
+// size of one font pixel on screen
+const HUD_SCALE: f32 = 1.0;
+// distance from the top left corner of the window
+const HUD_MARGIN: f32 = 8.0;
+const HUD_PADDING: f32 = 4.0;
+
+// how much of a shader error fits on screen
+const ERROR_LINES: usize = 12;
+const ERROR_WIDTH: usize = 96;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+// orbit polyline, sizes in window pixels
+const ORBIT_LINE_WIDTH: f32 = 1.5;
+const ORBIT_MARKER_SIZE: f32 = 5.0;
+const ORBIT_LINE_COLOR: [f32; 4] = [1.0, 0.8, 0.2, 0.8];
+const ORBIT_MARKER_COLOR: [f32; 4] = [1.0, 0.4, 0.1, 1.0];
+const ORBIT_START_COLOR: [f32; 4] = [0.2, 1.0, 0.4, 1.0];
+
+const FRAME_WIDTH: f32 = 1.0;
+const FRAME_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+
+// tiles of the main image still waiting for compute
+const TILE_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.25];
+
+// current view on the minimap
+const MARKER_WIDTH: f32 = 1.5;
+const MARKER_COLOR: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+const CROSSHAIR_SIZE: f32 = 6.0;
+
+#[repr(C)]
+#[derive(BufferContents, VertexTrait)]
+pub struct OverlayVertex {
+    #[format(R32G32_SFLOAT)]
+    position: [f32; 2],
+    #[format(R32G32_SFLOAT)]
+    tex_coords: [f32; 2],
+    #[format(R32G32B32A32_SFLOAT)]
+    color: [f32; 4],
+}
+
+// text and shape overlay drawn on top of the fractal in the graphics pass
+pub struct Hud {
+    pipeline: Arc<GraphicsPipeline>,
+    atlas_set: Arc<PersistentDescriptorSet>,
+}
+
+impl Hud {
+    pub fn new(device: Arc<Device>,
+            memory_allocator: &StandardMemoryAllocator,
+            command_buffer_allocator: &StandardCommandBufferAllocator,
+            descriptor_set_allocator: &StandardDescriptorSetAllocator,
+            queue: Arc<Queue>,
+            subpass: Subpass) -> Hud {
+        let vertex_shader = overlay::vs::load(device.clone()).unwrap();
+        let fragment_shader = overlay::fs::load(device.clone()).unwrap();
+
+        let pipeline = GraphicsPipeline::start()
+            .render_pass(subpass.clone())
+            .vertex_shader(vertex_shader.entry_point("main").unwrap(), ())
+            .fragment_shader(fragment_shader.entry_point("main").unwrap(), ())
+            .input_assembly_state(Default::default())
+            .vertex_input_state(OverlayVertex::per_vertex())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .color_blend_state(ColorBlendState::new(subpass.num_color_attachments()).blend_alpha())
+            .build(device.clone())
+            .unwrap();
+
+        // upload the font atlas once, it never changes
+        let mut upload_builder = AutoCommandBufferBuilder::primary(
+            command_buffer_allocator,
+            queue.queue_family_index(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+
+        let atlas = ImmutableImage::from_iter(
+            memory_allocator,
+            font::atlas(),
+            ImageDimensions::Dim2d {
+                width: font::ATLAS_WIDTH,
+                height: font::ATLAS_HEIGHT,
+                array_layers: 1,
+            },
+            MipmapsCount::One,
+            Format::R8_UNORM,
+            &mut upload_builder,
+        )
+        .unwrap();
+
+        upload_builder
+            .build()
+            .unwrap()
+            .execute(queue)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        // nearest filtering keeps the glyphs crisp
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Nearest,
+                min_filter: Filter::Nearest,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let set_layout = pipeline.layout().set_layouts().get(0).unwrap();
+        let atlas_set = PersistentDescriptorSet::new(
+            descriptor_set_allocator,
+            set_layout.clone(),
+            [WriteDescriptorSet::image_view_sampler(
+                0,
+                ImageView::new_default(atlas).unwrap(),
+                sampler,
+            )],
+        )
+        .unwrap();
+
+        Hud {
+            pipeline,
+            atlas_set,
+        }
+    }
+
+    // records the overlay into an already started render pass
+    pub fn draw(&self,
+            builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            memory_allocator: &StandardMemoryAllocator,
+            vertices: Vec<OverlayVertex>) {
+        if vertices.is_empty() {
+            return;
+        }
+
+        let vertex_buffer = Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::VERTEX_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            vertices,
+        )
+        .unwrap();
+
+        let vertex_count = vertex_buffer.len() as u32;
+
+        builder
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                self.pipeline.layout().clone(),
+                0,
+                self.atlas_set.clone(),
+            )
+            .bind_vertex_buffers(0, vertex_buffer)
+            .draw(vertex_count, 1, 0, 0)
+            .unwrap();
+    }
+}
+
+// the lines shown by the overlay, record_time is the cpu time spent recording the last frame.
+// the gpu runs behind it, so no frame rate is derived from it
+pub fn overlay_text(camera: &RenderCamera, auto_iters: bool, record_time: f32, cursor: Option<[f32; 2]>, window_size: [f32; 2]) -> Vec<String> {
+    let center = camera.to_complex([window_size[0] / 2.0, window_size[1] / 2.0], window_size);
+
+    let mut lines = vec![
+        format!("center {}", format_complex(center)),
+        format!("zoom   {:.3e}x", camera.zoom),
+        format!("angle  {:.1} deg", camera.rotation.to_degrees()),
+        format!("iters  {}{}", camera.max_iters, if auto_iters { " (auto)" } else { "" }),
+        format!("color  {} / {}", camera.coloring.name(), camera.interior.name()),
+        format!("record {:.1} ms", record_time * 1000.0),
+    ];
+
+    match cursor {
+        Some(pos) => lines.push(format!("cursor {}", format_complex(camera.to_complex(pos, window_size)))),
+        None => lines.push("cursor -".to_string()),
+    }
+
+    lines
+}
+
+pub fn orbit_text(orbit: &Orbit) -> Vec<String> {
+    let escape = match orbit.escape_iter {
+        Some(i) => format!("{}", i),
+        None => "never".to_string(),
+    };
+
+    let period = match orbit.period {
+        Some(p) => format!("{}", p),
+        None => "-".to_string(),
+    };
+
+    vec![
+        format!("orbit  {}", format_complex([orbit.c[0] as f32, orbit.c[1] as f32])),
+        format!("escape {}", escape),
+        format!("period {}", period),
+        format!("|z|    {:.7}", orbit.final_abs()),
+    ]
+}
+
+// a shader that failed to reload, the start of the compiler output
+pub fn error_text(error: &str) -> Vec<String> {
+    error
+        .lines()
+        .take(ERROR_LINES)
+        .map(|line| line.chars().take(ERROR_WIDTH).collect())
+        .collect()
+}
+
+fn format_complex(z: [f32; 2]) -> String {
+    format!("{:+.7} {:+.7}i", z[0], z[1])
+}
+
+// where the hud text block starts
+pub fn hud_origin() -> [f32; 2] {
+    [HUD_MARGIN, HUD_MARGIN]
+}
+
+// builds two triangles per glyph plus a translucent backdrop behind the block of text,
+// origin is the top left corner of the first glyph in window pixels
+pub fn layout_text(lines: &[String], origin: [f32; 2], window_size: [f32; 2]) -> Vec<OverlayVertex> {
+    let glyph_w = font::GLYPH_WIDTH as f32 * HUD_SCALE;
+    let glyph_h = font::GLYPH_HEIGHT as f32 * HUD_SCALE;
+
+    let columns = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+    let mut vertices = Vec::new();
+
+    if lines.is_empty() {
+        return vertices;
+    }
+
+    push_quad(
+        &mut vertices,
+        window_size,
+        [origin[0] - HUD_PADDING, origin[1] - HUD_PADDING],
+        [origin[0] + columns as f32 * glyph_w + HUD_PADDING, origin[1] + lines.len() as f32 * glyph_h + HUD_PADDING],
+        solid_uv(),
+        BACKGROUND_COLOR,
+    );
+
+    for (row, line) in lines.iter().enumerate() {
+        for (col, c) in line.chars().enumerate() {
+            if c == ' ' {
+                continue;
+            }
+
+            let min = [origin[0] + col as f32 * glyph_w, origin[1] + row as f32 * glyph_h];
+            let max = [min[0] + glyph_w, min[1] + glyph_h];
+
+            push_quad(&mut vertices, window_size, min, max, font::glyph_uv(font::glyph_index(c)), TEXT_COLOR);
+        }
+    }
+
+    vertices
+}
+
+// polyline through the orbit with a marker on every point, c gets its own color
+pub fn layout_orbit(orbit: &Orbit, camera: &RenderCamera, window_size: [f32; 2]) -> Vec<OverlayVertex> {
+    let mut vertices = Vec::new();
+
+    let pixels: Vec<[f32; 2]> = orbit.points
+        .iter()
+        .map(|z| camera.to_pixel([z[0] as f32, z[1] as f32], window_size))
+        .collect();
+
+    for segment in pixels.windows(2) {
+        push_line(&mut vertices, window_size, segment[0], segment[1], ORBIT_LINE_WIDTH, ORBIT_LINE_COLOR);
+    }
+
+    for (i, p) in pixels.iter().enumerate() {
+        let color = if i == 0 { ORBIT_START_COLOR } else { ORBIT_MARKER_COLOR };
+        let half = ORBIT_MARKER_SIZE / 2.0;
+
+        push_quad(&mut vertices, window_size, [p[0] - half, p[1] - half], [p[0] + half, p[1] + half], solid_uv(), color);
+    }
+
+    vertices
+}
+
+// outline around a window pixel rectangle
+pub fn layout_frame(min: [f32; 2], max: [f32; 2], window_size: [f32; 2]) -> Vec<OverlayVertex> {
+    let mut vertices = Vec::new();
+
+    let corners = [min, [max[0], min[1]], max, [min[0], max[1]]];
+
+    for i in 0..corners.len() {
+        push_line(&mut vertices, window_size, corners[i], corners[(i + 1) % corners.len()], FRAME_WIDTH, FRAME_COLOR);
+    }
+
+    vertices
+}
+
+// outlines of pending tiles, their pixels map 1:1 to the window
+pub fn layout_tiles(tiles: &[([u32; 2], u32)], window_size: [f32; 2]) -> Vec<OverlayVertex> {
+    let mut vertices = Vec::new();
+
+    for (origin, size) in tiles {
+        let min = [origin[0] as f32, origin[1] as f32];
+        let max = [(min[0] + *size as f32).min(window_size[0]), (min[1] + *size as f32).min(window_size[1])];
+
+        let corners = [min, [max[0], min[1]], max, [min[0], max[1]]];
+
+        for i in 0..corners.len() {
+            push_line(&mut vertices, window_size, corners[i], corners[(i + 1) % corners.len()], FRAME_WIDTH, TILE_COLOR);
+        }
+    }
+
+    vertices
+}
+
+// viewport box on the minimap, or a crosshair with the zoom depth once the box is too small.
+// label_bottom_left is where the zoom depth text ends up
+pub fn layout_marker(marker: &Marker, zoom: f32, label_bottom_left: [f32; 2], window_size: [f32; 2]) -> Vec<OverlayVertex> {
+    let mut vertices = Vec::new();
+
+    match marker {
+        Marker::Box(corners) => {
+            for i in 0..corners.len() {
+                push_line(&mut vertices, window_size, corners[i], corners[(i + 1) % corners.len()], MARKER_WIDTH, MARKER_COLOR);
+            }
+        }
+
+        Marker::Crosshair(c) => {
+            push_line(&mut vertices, window_size, [c[0] - CROSSHAIR_SIZE, c[1]], [c[0] + CROSSHAIR_SIZE, c[1]], MARKER_WIDTH, MARKER_COLOR);
+            push_line(&mut vertices, window_size, [c[0], c[1] - CROSSHAIR_SIZE], [c[0], c[1] + CROSSHAIR_SIZE], MARKER_WIDTH, MARKER_COLOR);
+
+            let glyph_h = font::GLYPH_HEIGHT as f32 * HUD_SCALE;
+            let origin = [label_bottom_left[0] + HUD_PADDING, label_bottom_left[1] - glyph_h - HUD_PADDING];
+
+            vertices.extend(layout_text(&[format!("depth {:.3e}x", zoom)], origin, window_size));
+        }
+    }
+
+    vertices
+}
+
+// uv that samples the middle of the solid atlas slot
+fn solid_uv() -> ([f32; 2], [f32; 2]) {
+    let (min, max) = font::glyph_uv(font::SOLID_GLYPH);
+    let mid = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0];
+
+    (mid, mid)
+}
+
+fn push_line(vertices: &mut Vec<OverlayVertex>, window_size: [f32; 2], a: [f32; 2], b: [f32; 2], width: f32, color: [f32; 4]) {
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let len = (d[0] * d[0] + d[1] * d[1]).sqrt();
+
+    if len <= f32::EPSILON {
+        return;
+    }
+
+    // half width offset perpendicular to the segment
+    let n = [-d[1] / len * width / 2.0, d[0] / len * width / 2.0];
+    let (uv, _) = solid_uv();
+
+    let to_ndc = |p: [f32; 2]| [p[0] / window_size[0] * 2.0 - 1.0, p[1] / window_size[1] * 2.0 - 1.0];
+
+    let corners = [
+        to_ndc([a[0] + n[0], a[1] + n[1]]),
+        to_ndc([b[0] + n[0], b[1] + n[1]]),
+        to_ndc([a[0] - n[0], a[1] - n[1]]),
+        to_ndc([b[0] - n[0], b[1] - n[1]]),
+    ];
+
+    for i in [0, 1, 2, 2, 1, 3] {
+        vertices.push(OverlayVertex { position: corners[i], tex_coords: uv, color });
+    }
+}
+
+// min and max are in window pixels, origin top left
+fn push_quad(vertices: &mut Vec<OverlayVertex>, window_size: [f32; 2], min: [f32; 2], max: [f32; 2], uv: ([f32; 2], [f32; 2]), color: [f32; 4]) {
+    let to_ndc = |p: [f32; 2]| [p[0] / window_size[0] * 2.0 - 1.0, p[1] / window_size[1] * 2.0 - 1.0];
+
+    let (uv_min, uv_max) = uv;
+
+    let corners = [
+        (to_ndc([min[0], min[1]]), [uv_min[0], uv_min[1]]),
+        (to_ndc([max[0], min[1]]), [uv_max[0], uv_min[1]]),
+        (to_ndc([min[0], max[1]]), [uv_min[0], uv_max[1]]),
+        (to_ndc([max[0], max[1]]), [uv_max[0], uv_max[1]]),
+    ];
+
+    for i in [0, 1, 2, 2, 1, 3] {
+        let (position, tex_coords) = corners[i];
+
+        vertices.push(OverlayVertex { position, tex_coords, color });
+    }
+}
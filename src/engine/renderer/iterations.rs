@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::image::{ImageAccess, StorageImage};
+use vulkano::image::view::ImageView;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
+
+use super::render::BASE_ITERS;
+
+use crate::engine::shaders::mandelbrot::stats::{PushConstants, Stats};
+
+const MIN_ITERS: i32 = 10;
+const MAX_ITERS: i32 = 100_000;
+
+// step of E/Q
+const MANUAL_STEP: i32 = 10;
+
+// bounds of what the statistics can do to the zoom based limit
+const MIN_SCALE: f32 = 0.25;
+const MAX_SCALE: f32 = 16.0;
+
+// the scale goes up or down by this, the same factor both ways so a step up can be undone
+const SCALE_STEP: f32 = 1.25;
+
+// limit pixels right next to escaped ones are the detail the limit cuts off. above the high
+// fraction the limit goes up, below the low one it may go down, in between it stays. the gap
+// keeps one step from undoing the previous
+const HIGH_BOUNDARY: f32 = 0.05;
+const LOW_BOUNDARY: f32 = 0.01;
+// more late escapes than this cut off detail as well
+const LATE_FRACTION: f32 = 0.01;
+// going down also needs every escape below this fraction of the limit, otherwise the
+// next view would have late escapes and go right back up
+const LOW_HIGHEST: f32 = 0.25;
+
+// limit for a zoom level before any statistics, grows with the zoom depth
+pub fn zoom_iters(zoom: f32) -> u32 {
+    (BASE_ITERS as f32 * (1.0 + zoom.max(1.0).log2() * 0.5)) as u32
+}
+
+// picks max_iters. in auto mode it follows the zoom depth, scaled by how the last finished
+// view came out, with E/Q adding an offset on top. otherwise E/Q set it directly
+pub struct IterationLimit {
+    pub auto: bool,
+
+    offset: i32,
+    manual: u32,
+    scale: f32
+}
+
+impl IterationLimit {
+    pub fn new() -> IterationLimit {
+        IterationLimit {
+            auto: true,
+
+            offset: 0,
+            manual: BASE_ITERS,
+            scale: 1.0
+        }
+    }
+
+    pub fn limit(&self, zoom: f32) -> u32 {
+        if !self.auto {
+            return self.manual;
+        }
+
+        let auto = (zoom_iters(zoom) as f32 * self.scale) as i32;
+
+        (auto + self.offset).clamp(MIN_ITERS, MAX_ITERS) as u32
+    }
+
+    // one E (up) or Q (down) press
+    pub fn step(&mut self, up: bool) {
+        let step = if up { MANUAL_STEP } else { -MANUAL_STEP };
+
+        if self.auto {
+            self.offset += step;
+        } else {
+            self.manual = (self.manual as i32 + step).clamp(MIN_ITERS, MAX_ITERS) as u32;
+        }
+    }
+
+    // leaving auto mode keeps whatever limit it had picked
+    pub fn toggle_auto(&mut self, zoom: f32) {
+        if self.auto {
+            self.manual = self.limit(zoom);
+        } else {
+            self.offset = 0;
+        }
+
+        self.auto = !self.auto;
+    }
+
+    // a limit picked from outside, e.g. by `Engine::set_view`. auto mode goes off
+    pub fn set(&mut self, max_iters: u32) {
+        self.auto = false;
+        self.manual = max_iters;
+    }
+
+    // adjusts the scale from a finished view taken with max_iters, true if the limit changes
+    pub fn feed(&mut self, stats: &Stats, max_iters: u32) -> bool {
+        if !self.auto || stats.exact == 0 {
+            return false;
+        }
+
+        let late = stats.late as f32 / stats.escaped.max(1) as f32;
+        let boundary = stats.boundary as f32 / stats.exact as f32;
+        let highest = f32::from_bits(stats.highest) / max_iters as f32;
+
+        let scale = if late > LATE_FRACTION || boundary > HIGH_BOUNDARY {
+            (self.scale * SCALE_STEP).min(MAX_SCALE)
+        } else if stats.late == 0 && boundary < LOW_BOUNDARY && highest < LOW_HIGHEST {
+            (self.scale / SCALE_STEP).max(MIN_SCALE)
+        } else {
+            self.scale
+        };
+
+        let changed = scale != self.scale;
+        self.scale = scale;
+
+        changed
+    }
+}
+
+// records the statistics pass over the samples of a finished view, the buffer can be read
+// once the gpu is done with it
+pub fn record_stats(builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        memory_allocator: &StandardMemoryAllocator,
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+        pipeline: Arc<ComputePipeline>,
+        samples: Arc<ImageView<StorageImage>>,
+        max_iters: u32) -> Subbuffer<Stats> {
+    let buffer = Buffer::from_data(
+        memory_allocator,
+        BufferCreateInfo {
+            usage: BufferUsage::STORAGE_BUFFER,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            usage: MemoryUsage::Download,
+            ..Default::default()
+        },
+        Stats {
+            exact: 0,
+            escaped: 0,
+            late: 0,
+            limit: 0,
+            boundary: 0,
+            highest: 0,
+        },
+    )
+    .unwrap();
+
+    let set = PersistentDescriptorSet::new(
+        descriptor_set_allocator,
+        pipeline.layout().set_layouts().get(0).unwrap().clone(),
+        [
+            WriteDescriptorSet::image_view(0, samples.clone()),
+            WriteDescriptorSet::buffer(1, buffer.clone()),
+        ],
+    )
+    .unwrap();
+
+    let [width, height] = samples.image().dimensions().width_height();
+
+    builder
+        .bind_pipeline_compute(pipeline.clone())
+        .bind_descriptor_sets(
+            PipelineBindPoint::Compute,
+            pipeline.layout().clone(),
+            0,
+            set,
+        )
+        .push_constants(pipeline.layout().clone(), 0, PushConstants { max_iters })
+        .dispatch([(width + 7) / 8, (height + 7) / 8, 1])
+        .unwrap();
+
+    buffer
+}
@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::image::{ImageCreateFlags, ImageDimensions, ImageUsage, StorageImage};
+use vulkano::image::view::ImageView;
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::pipeline::{ComputePipeline, GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::sampler::{Filter, Sampler, SamplerCreateInfo};
+
+use super::helper;
+
+use crate::engine::shaders::julia;
+
+// side length of the inset in pixels, both in the storage image and on screen
+const JULIA_SIZE: u32 = 192;
+// distance from the bottom right corner of the window
+const JULIA_MARGIN: f32 = 8.0;
+// the inset always shows the dynamical plane from -2 to 2
+const JULIA_SCALE: f32 = 1.0;
+
+// picture in picture julia set for the c under the cursor
+pub struct Julia {
+    pipeline: Arc<ComputePipeline>,
+    compute_set: Arc<PersistentDescriptorSet>,
+    sampled_set: Arc<PersistentDescriptorSet>,
+
+    // c and iteration limit the image currently holds, none until the first dispatch
+    rendered: Option<([f32; 2], u32)>
+}
+
+impl Julia {
+    pub fn new(device: Arc<Device>,
+            memory_allocator: &StandardMemoryAllocator,
+            descriptor_set_allocator: &StandardDescriptorSetAllocator,
+            queue_family_indices: &[u32],
+            graphics_pipeline: &GraphicsPipeline) -> Julia {
+        let shader = julia::cs::load(device.clone()).unwrap();
+
+        let pipeline = ComputePipeline::new(
+            device.clone(),
+            shader.entry_point("main").unwrap(),
+            &(),
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        let image = StorageImage::with_usage(
+            memory_allocator,
+            ImageDimensions::Dim2d {
+                width: JULIA_SIZE,
+                height: JULIA_SIZE,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8_UNORM,
+            ImageUsage::STORAGE | ImageUsage::SAMPLED,
+            ImageCreateFlags::empty(),
+            queue_family_indices.iter().copied(),
+        )
+        .unwrap();
+
+        let view = ImageView::new_default(image).unwrap();
+
+        let compute_set = PersistentDescriptorSet::new(
+            descriptor_set_allocator,
+            pipeline.layout().set_layouts().get(0).unwrap().clone(),
+            [WriteDescriptorSet::image_view(0, view.clone())],
+        )
+        .unwrap();
+
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // composited with the main graphics pipeline, same layout as the fractal image
+        let sampled_set = PersistentDescriptorSet::new(
+            descriptor_set_allocator,
+            graphics_pipeline.layout().set_layouts().get(0).unwrap().clone(),
+            [WriteDescriptorSet::image_view_sampler(0, view, sampler)],
+        )
+        .unwrap();
+
+        Julia {
+            pipeline,
+            compute_set,
+            sampled_set,
+
+            rendered: None
+        }
+    }
+
+    pub fn dispatch(&mut self,
+            builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            c: [f32; 2],
+            max_iters: u32) {
+        // the image already shows this one
+        if self.rendered == Some((c, max_iters)) {
+            return;
+        }
+
+        let push_constants = julia::cs::PushConstants {
+            c,
+            scale: JULIA_SCALE,
+            max_iters,
+        };
+
+        builder
+            .bind_pipeline_compute(self.pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                self.pipeline.layout().clone(),
+                0,
+                self.compute_set.clone(),
+            )
+            .push_constants(self.pipeline.layout().clone(), 0, push_constants)
+            .dispatch([(JULIA_SIZE + 7) / 8, (JULIA_SIZE + 7) / 8, 1])
+            .unwrap();
+
+        self.rendered = Some((c, max_iters));
+    }
+
+    pub fn is_rendered(&self) -> bool {
+        return self.rendered.is_some();
+    }
+
+    // draws the inset into an already started render pass
+    pub fn draw(&self,
+            builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            memory_allocator: &StandardMemoryAllocator,
+            graphics_pipeline: Arc<GraphicsPipeline>,
+            window_size: [f32; 2]) {
+        let (min, max) = match inset_rect(window_size) {
+            Some(rect) => rect,
+            None => return,
+        };
+
+        helper::draw_image_quad(
+            builder,
+            memory_allocator,
+            graphics_pipeline,
+            self.sampled_set.clone(),
+            min,
+            max,
+            window_size,
+        );
+    }
+}
+
+// window pixel rectangle of the inset, none if the window is too small to fit it
+pub fn inset_rect(window_size: [f32; 2]) -> Option<([f32; 2], [f32; 2])> {
+    let size = JULIA_SIZE as f32;
+
+    if window_size[0] < size + 2.0 * JULIA_MARGIN || window_size[1] < size + 2.0 * JULIA_MARGIN {
+        return None;
+    }
+
+    let max = [window_size[0] - JULIA_MARGIN, window_size[1] - JULIA_MARGIN];
+    let min = [max[0] - size, max[1] - size];
+
+    Some((min, max))
+}
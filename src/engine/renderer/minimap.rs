@@ -0,0 +1,228 @@
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer};
+use vulkano::buffer::Subbuffer;
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::image::ImageUsage;
+use vulkano::memory::allocator::StandardMemoryAllocator;
+use vulkano::pipeline::{ComputePipeline, GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::sampler::{Filter, Sampler, SamplerCreateInfo};
+
+use super::helper;
+use super::coloring::{Coloring, Interior};
+use super::colorize;
+use super::frame::{self, ORBITS_FORMAT, SAMPLES_FORMAT};
+use super::render::RenderCamera;
+
+// side length of the minimap in pixels, both in the storage image and on screen
+const MINIMAP_SIZE: u32 = 160;
+// distance from the bottom left corner of the window
+const MINIMAP_MARGIN: f32 = 8.0;
+const MINIMAP_ITERS: u32 = 200;
+
+// below this size in pixels the viewport box turns into a crosshair
+const MIN_BOX_SIZE: f32 = 4.0;
+
+// overview of the whole set, rendered once with the main kernel and kept around
+pub struct Minimap {
+    compute_set: Arc<PersistentDescriptorSet>,
+    colorize_set: Arc<PersistentDescriptorSet>,
+    sampled_set: Arc<PersistentDescriptorSet>,
+
+    rendered: bool
+}
+
+// what the overlay should draw on top of the minimap for the current view
+pub enum Marker {
+    // corners of the viewport in window pixels, it's only axis aligned without rotation
+    Box([[f32; 2]; 4]),
+    // view is too small to see, crosshair at its center in window pixels
+    Crosshair([f32; 2])
+}
+
+impl Minimap {
+    pub fn new(device: Arc<Device>,
+            memory_allocator: &StandardMemoryAllocator,
+            descriptor_set_allocator: &StandardDescriptorSetAllocator,
+            queue_family_indices: &[u32],
+            compute_family: u32,
+            compute_pipeline: &ComputePipeline,
+            colorize_pipeline: &ComputePipeline,
+            cdf: Subbuffer<[f32]>,
+            graphics_pipeline: &GraphicsPipeline) -> Minimap {
+        let dims = [MINIMAP_SIZE, MINIMAP_SIZE];
+
+        let samples = frame::new_image(memory_allocator, dims, SAMPLES_FORMAT, ImageUsage::STORAGE, &[compute_family]);
+        let orbits = frame::new_image(memory_allocator, dims, ORBITS_FORMAT, ImageUsage::STORAGE, &[compute_family]);
+        let view = frame::new_image(
+            memory_allocator,
+            dims,
+            Format::R8G8B8A8_UNORM,
+            ImageUsage::STORAGE | ImageUsage::SAMPLED,
+            queue_family_indices,
+        );
+
+        let states = frame::new_states(memory_allocator, 1);
+        let compute_set = frame::raw_set(descriptor_set_allocator, compute_pipeline, &samples, &orbits, states);
+        let colorize_set = frame::colorize_set(descriptor_set_allocator, colorize_pipeline, &samples, &orbits, &view, cdf);
+
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let sampled_set = PersistentDescriptorSet::new(
+            descriptor_set_allocator,
+            graphics_pipeline.layout().set_layouts().get(0).unwrap().clone(),
+            [WriteDescriptorSet::image_view_sampler(0, view, sampler)],
+        )
+        .unwrap();
+
+        Minimap {
+            compute_set,
+            colorize_set,
+            sampled_set,
+
+            rendered: false
+        }
+    }
+
+    // records the one time render of the overview, does nothing after the first call
+    pub fn dispatch(&mut self,
+            builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            compute_pipeline: Arc<ComputePipeline>,
+            colorize_pipeline: Arc<ComputePipeline>) {
+        if self.rendered {
+            return;
+        }
+
+        let camera = overview_camera();
+
+        let push_constants = camera.push_constants(1, false, [0, 0]);
+
+        builder
+            .bind_pipeline_compute(compute_pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Compute,
+                compute_pipeline.layout().clone(),
+                0,
+                self.compute_set.clone(),
+            )
+            .push_constants(compute_pipeline.layout().clone(), 0, push_constants)
+            .dispatch([(MINIMAP_SIZE + 7) / 8, (MINIMAP_SIZE + 7) / 8, 1])
+            .unwrap();
+
+        colorize::record(builder, colorize_pipeline, self.colorize_set.clone(), &camera, [MINIMAP_SIZE, MINIMAP_SIZE], 0);
+
+        self.rendered = true;
+    }
+
+    pub fn is_rendered(&self) -> bool {
+        return self.rendered;
+    }
+
+    // the kernel changed, the overview is rendered again on the next dispatch
+    pub fn invalidate(&mut self) {
+        self.rendered = false;
+    }
+
+    // draws the overview into an already started render pass
+    pub fn draw(&self,
+            builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            memory_allocator: &StandardMemoryAllocator,
+            graphics_pipeline: Arc<GraphicsPipeline>,
+            window_size: [f32; 2]) {
+        let (min, max) = match minimap_rect(window_size) {
+            Some(rect) => rect,
+            None => return,
+        };
+
+        helper::draw_image_quad(
+            builder,
+            memory_allocator,
+            graphics_pipeline,
+            self.sampled_set.clone(),
+            min,
+            max,
+            window_size,
+        );
+    }
+}
+
+// fixed view the overview is rendered with, frames the whole set
+fn overview_camera() -> RenderCamera {
+    RenderCamera {
+        translation: [-0.75, 0.0],
+        zoom: 1.5,
+        rotation: 0.0,
+        max_iters: MINIMAP_ITERS,
+        coloring: Coloring::Iterations,
+        interior: Interior::Flat
+    }
+}
+
+// window pixel rectangle of the minimap, none if the window is too small to fit it
+pub fn minimap_rect(window_size: [f32; 2]) -> Option<([f32; 2], [f32; 2])> {
+    let size = MINIMAP_SIZE as f32;
+
+    if window_size[0] < size + 2.0 * MINIMAP_MARGIN || window_size[1] < size + 2.0 * MINIMAP_MARGIN {
+        return None;
+    }
+
+    let min = [MINIMAP_MARGIN, window_size[1] - MINIMAP_MARGIN - size];
+    let max = [min[0] + size, min[1] + size];
+
+    Some((min, max))
+}
+
+// complex point under a window pixel, none if the pixel is outside the minimap
+pub fn point_at(pixel: [f32; 2], window_size: [f32; 2]) -> Option<[f32; 2]> {
+    let (min, max) = minimap_rect(window_size)?;
+
+    if pixel[0] < min[0] || pixel[0] > max[0] || pixel[1] < min[1] || pixel[1] > max[1] {
+        return None;
+    }
+
+    let size = MINIMAP_SIZE as f32;
+
+    Some(overview_camera().to_complex([pixel[0] - min[0], pixel[1] - min[1]], [size, size]))
+}
+
+// where the current view sits on the minimap
+pub fn marker(camera: &RenderCamera, window_size: [f32; 2]) -> Option<Marker> {
+    let (min, _) = minimap_rect(window_size)?;
+
+    let size = MINIMAP_SIZE as f32;
+    let overview = overview_camera();
+
+    let to_minimap = |pixel: [f32; 2]| {
+        let p = overview.to_pixel(camera.to_complex(pixel, window_size), [size, size]);
+
+        [p[0] + min[0], p[1] + min[1]]
+    };
+
+    let corners = [
+        to_minimap([0.0, 0.0]),
+        to_minimap([window_size[0], 0.0]),
+        to_minimap(window_size),
+        to_minimap([0.0, window_size[1]]),
+    ];
+
+    let side = |a: [f32; 2], b: [f32; 2]| ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+
+    if side(corners[0], corners[1]) < MIN_BOX_SIZE || side(corners[0], corners[3]) < MIN_BOX_SIZE {
+        let center = to_minimap([window_size[0] / 2.0, window_size[1] / 2.0]);
+
+        return Some(Marker::Crosshair(center));
+    }
+
+    Some(Marker::Box(corners))
+}
@@ -1,2 +1,17 @@
 pub mod render;
-mod helper;
\ No newline at end of file
+mod cache;
+pub mod coloring;
+mod colorize;
+mod helper;
+pub mod histogram;
+mod iterations;
+mod hud;
+mod font;
+mod frame;
+mod julia;
+mod minimap;
+pub mod screenshot;
+pub mod shader_watch;
+mod reproject;
+mod subdivide;
+mod tiles;
@@ -1,58 +1,70 @@
-
-use std::ops::Range;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Instant;
 
-use image::{ImageBuffer, Rgba, DynamicImage, GrayImage};
-
-use vulkano::pipeline::graphics::vertex_input::{VertexBufferDescription, VertexInputState, Vertex as VertexTrait};
+use vulkano::pipeline::graphics::vertex_input::Vertex as VertexTrait;
 use vulkano::pipeline::graphics::viewport::{ViewportState, Viewport};
-use vulkano::render_pass::{Subpass, RenderPass, Framebuffer, FramebufferCreateInfo};
+use vulkano::render_pass::{Subpass, RenderPass};
 use vulkano::sampler::{Sampler, SamplerCreateInfo, Filter, SamplerMipmapMode};
 use vulkano::shader::ShaderModule;
-use vulkano::{VulkanLibrary, swapchain, format};
-use vulkano::device::physical::PhysicalDevice;
-use vulkano::instance::{Instance, InstanceCreateInfo};
 
-use vulkano::command_buffer::allocator::{StandardCommandBufferAllocator, StandardCommandBufferAllocatorCreateInfo};
-use vulkano::command_buffer::{AutoCommandBufferBuilder, CopyImageToBufferInfo, CommandBufferUsage, RenderPassBeginInfo, SubpassContents, PrimaryCommandBufferAbstract};
+use vulkano::command_buffer::allocator::StandardCommandBufferAllocator;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, ClearColorImageInfo, CommandBufferUsage, RenderPassBeginInfo, SubpassContents};
 
 use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
-use vulkano::descriptor_set::{WriteDescriptorSet, PersistentDescriptorSet};
 
-use vulkano::device::{Device, Queue, DeviceCreateInfo, QueueCreateInfo, QueueFlags, DeviceExtensions};
+use vulkano::device::{Device, Queue};
 
 use vulkano::pipeline::{PipelineBindPoint, ComputePipeline, Pipeline, GraphicsPipeline};
 
 use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer, BufferContents};
 
-use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator, MemoryAllocator, GenericMemoryAllocator};
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator, GenericMemoryAllocator};
 
-// mandelbrot testing
-use vulkano::image::{ImageDimensions, StorageImage, SwapchainImage, ImageUsage, ImageAccess};
+use vulkano::image::{StorageImage, ImageAccess};
 use vulkano::image::view::ImageView;
-use vulkano::format::Format;
 
-use vulkano::swapchain::{Surface, Swapchain, SwapchainCreateInfo, SwapchainPresentInfo};
-use vulkano::sync::{self, GpuFuture};
+use vulkano::query::QueryResultFlags;
+use vulkano::sync::{GpuFuture, PipelineStage};
 use vulkano_util::context::VulkanoContext;
-use vulkano_util::renderer::{DEFAULT_IMAGE_FORMAT, VulkanoWindowRenderer};
 use vulkano_util::window::VulkanoWindows;
-use vulkano_win::create_surface_from_winit;
 use winit::window::Window;
 
-use super::helper;
+use super::cache::{self, TileCache};
+use super::frame::{FrameSlot, TargetSets};
+use super::coloring::{Coloring, Interior};
+use super::colorize;
+use super::histogram::Histogram;
+use super::iterations::{self, IterationLimit};
+use super::hud::{self, Hud};
+use super::julia::{self, Julia};
+use super::minimap::{self, Minimap};
+use super::screenshot;
+use super::shader_watch::{self, ShaderWatch, Stage};
+use super::reproject;
+use super::subdivide::{self, Subdivision};
+use super::tiles::TileQueue;
 
 use crate::engine::camera::Camera;
-use crate::engine::shaders::{self, mandelbrot};
+use crate::engine::orbit::Orbit;
+use crate::engine::shaders::mandelbrot;
+use crate::engine::view::Formula;
+
+pub const BASE_ITERS: u32 = 300;
 
-const BASE_ITERS: u32 = 300;
+// block size of the first pass after the view changes, halved every frame down to 1
+pub const COARSE_BLOCK: u32 = 8;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct RenderCamera {
     pub translation: [f32; 2],
     pub zoom: f32,
-    pub max_iters: u32
+    // radians, the view turns around the middle of the window
+    pub rotation: f32,
+    pub max_iters: u32,
+    pub coloring: Coloring,
+    pub interior: Interior
 }
 
 impl RenderCamera {
@@ -60,9 +72,104 @@ impl RenderCamera {
         RenderCamera { 
             translation: [0.0, 0.0],
             zoom: 1.0,
-            max_iters: BASE_ITERS
+            rotation: 0.0,
+            max_iters: BASE_ITERS,
+            coloring: Coloring::Iterations,
+            interior: Interior::Flat
+        }
+    }
+
+    // block_size 1 renders every pixel, see `COARSE_BLOCK`. with reuse pixels that already
+    // hold an exact sample are skipped. tile_offset is in blocks
+    pub fn push_constants(&self, block_size: u32, reuse: bool, tile_offset: [u32; 2]) -> mandelbrot::cs::PushConstants {
+        mandelbrot::cs::PushConstants {
+            scale: self.zoom.into(),
+            translation: self.translation,
+            max_iters: self.max_iters,
+            rotation: self.rotation,
+            block_size,
+            reuse: reuse as u32,
+            tile_offset,
+            supersample: self.coloring.supersamples() as u32,
+            resume_from: 0,
         }
     }
+
+    // whether samples taken for either camera hold the same values, only the coloring of
+    // the two may differ
+    pub fn same_sampling(&self, other: &RenderCamera) -> bool {
+        self.max_iters == other.max_iters && self.coloring.supersamples() == other.coloring.supersamples()
+    }
+
+    // whether samples taken for either camera can be kept as they are for the other
+    pub fn same_samples(&self, other: &RenderCamera) -> bool {
+        self.translation == other.translation
+            && self.zoom == other.zoom
+            && self.rotation == other.rotation
+            && self.same_sampling(other)
+    }
+
+    // whether other is this view with only a higher limit, samples that ran into this
+    // one's can carry on instead of starting over
+    pub fn resumes_at(&self, other: &RenderCamera) -> bool {
+        self.translation == other.translation
+            && self.zoom == other.zoom
+            && self.rotation == other.rotation
+            && self.coloring.supersamples() == other.coloring.supersamples()
+            && other.max_iters > self.max_iters
+    }
+
+    // size of a pixel in the complex plane for an image this many pixels high
+    pub fn pixel_size(&self, height: f32) -> f32 {
+        4.0 / (self.zoom * height)
+    }
+
+    // complex coordinate of a window pixel, mirrors the mapping in the `cs` kernel
+    pub fn to_complex(&self, pixel: [f32; 2], dims: [f32; 2]) -> [f32; 2] {
+        let ar = dims[0] / dims[1];
+        let x_norm = pixel[0] / dims[0];
+        let y_norm = pixel[1] / dims[1];
+
+        let p = [
+            ar * (x_norm * 4.0 / self.zoom) - (2.0 / self.zoom),
+            (y_norm * 4.0 / self.zoom) - (2.0 / self.zoom),
+        ];
+
+        let mid = self.middle(ar);
+        let d = rotate([p[0] - mid[0], p[1] - mid[1]], self.rotation);
+
+        [
+            mid[0] + d[0] + self.translation[0],
+            mid[1] + d[1] + self.translation[1],
+        ]
+    }
+
+    // inverse of `to_complex`
+    pub fn to_pixel(&self, point: [f32; 2], dims: [f32; 2]) -> [f32; 2] {
+        let ar = dims[0] / dims[1];
+
+        let mid = self.middle(ar);
+        let d = rotate([
+            point[0] - self.translation[0] - mid[0],
+            point[1] - self.translation[1] - mid[1],
+        ], -self.rotation);
+
+        let x_norm = (mid[0] + d[0] + 2.0 / self.zoom) * self.zoom / (4.0 * ar);
+        let y_norm = (mid[1] + d[1] + 2.0 / self.zoom) * self.zoom / 4.0;
+
+        [x_norm * dims[0], y_norm * dims[1]]
+    }
+
+    // unrotated offset of the window's middle from the translation
+    fn middle(&self, ar: f32) -> [f32; 2] {
+        [ar * (2.0 / self.zoom) - (2.0 / self.zoom), 0.0]
+    }
+}
+
+fn rotate(v: [f32; 2], angle: f32) -> [f32; 2] {
+    let (sin, cos) = angle.sin_cos();
+
+    [cos * v[0] - sin * v[1], sin * v[0] + cos * v[1]]
 }
 
 impl From<Camera> for RenderCamera {
@@ -70,7 +177,10 @@ impl From<Camera> for RenderCamera {
         RenderCamera {
             translation: cam.center,
             zoom: cam.zoom,
-            max_iters: (cam.zoom * BASE_ITERS as f32) as u32 / 2
+            rotation: cam.rotation,
+            max_iters: iterations::zoom_iters(cam.zoom),
+            coloring: Coloring::Iterations,
+            interior: Interior::Flat
         }
     }
 }
@@ -79,9 +189,9 @@ impl From<Camera> for RenderCamera {
 #[derive(BufferContents, VertexTrait)]
 pub struct Vertex {
     #[format(R32G32_SFLOAT)]
-    position: [f32; 2],
+    pub position: [f32; 2],
     #[format(R32G32_SFLOAT)]
-    tex_coords: [f32; 2],
+    pub tex_coords: [f32; 2],
 }
 
 const VERTICES: [Vertex; 3] = [
@@ -101,7 +211,6 @@ const VERTICES: [Vertex; 3] = [
 
 pub struct Renderer {
     window: VulkanoWindows,
-    render_target_id: usize,
     
     pub camera: RenderCamera,
 
@@ -110,27 +219,88 @@ pub struct Renderer {
     descriptor_set_allocator: StandardDescriptorSetAllocator,
     
     device: Arc<Device>,
-    data_offset: u32,
 
+    // the compute queue is on its own family when the device has one, the fractal is
+    // computed there while the graphics queue presents the previous frame
     compute_queue: Arc<Queue>,
     graphics_queue: Arc<Queue>,
+    // families images used by both queues are shared between, one if they're the same
+    queue_family_indices: Vec<u32>,
 
-    compute_shader: Arc<ShaderModule>,
     vertex_shader: Arc<ShaderModule>,
     fragment_shader: Arc<ShaderModule>,
 
     render_pass: Arc<RenderPass>,
     compute_pipeline: Arc<ComputePipeline>,
+    reproject_pipeline: Arc<ComputePipeline>,
+    stats_pipeline: Arc<ComputePipeline>,
+    subdivide_pipeline: Arc<ComputePipeline>,
+    colorize_pipeline: Arc<ComputePipeline>,
     graphics_pipeline: Arc<GraphicsPipeline>,
 
     delta_time: f32,
     previous_frame: Instant,
 
-    vertex_buffer: Subbuffer<[Vertex]>
+    vertex_buffer: Subbuffer<[Vertex]>,
+    sampler: Arc<Sampler>,
+
+    // built on first use and on resize, see `TargetSets` and `FrameSlot`
+    target_sets: Option<TargetSets>,
+    frames: HashMap<u32, FrameSlot>,
+    // nanoseconds per gpu timestamp tick, none if the queue can't write timestamps
+    timestamp_period: Option<f32>,
+
+    hud: Hud,
+    pub show_hud: bool,
+    // window pixel under the mouse, if it is inside the window
+    cursor: Option<[f32; 2]>,
+    pub orbit: Option<Orbit>,
+
+    julia: Julia,
+    pub show_julia: bool,
+
+    minimap: Minimap,
+    pub show_minimap: bool,
+
+    // scale of the screenshot to take with the next frame
+    screenshot_scale: Option<u32>,
+
+    // view and target the progressive passes belong to, anything sampled differently
+    // starts over. a different coloring only colors the samples again
+    refine_view: Option<(RenderCamera, Arc<ImageView<StorageImage>>)>,
+    // compute work left for that view
+    tiles: TileQueue,
+    // while the passes only carry on orbits from a lower limit, the lowest limit any exact
+    // pixel was sampled with
+    resume_from: Option<u32>,
+
+    // mariani-silver subdivision instead of progressive tiles, see `Subdivision`
+    subdivide: bool,
+    subdivision: Option<Subdivision>,
+
+    // views composed from cached tiles instead, see `TileCache`
+    cached: bool,
+    cache: TileCache,
+    // which `cs` kernel the cached tiles come from, see `cache::kernel_key`
+    compute_key: u64,
+
+    // glsl files compiled at runtime over the built in shaders, see `ShaderWatch`
+    shader_watch: Option<ShaderWatch>,
+    // what the `cs` and `subdivide` kernels iterate, see `set_formula`
+    formula: Formula,
+
+    pub iterations: IterationLimit,
+    pub coloring: Coloring,
+    pub interior: Interior,
+    histogram: Histogram,
+    // statistics of the last finished view and the limit it was rendered with, read back
+    // once the gpu is done with them
+    pending_stats: Option<(u32, Subbuffer<mandelbrot::stats::Stats>)>
 }
 
 impl Renderer {
-    pub fn new(mut window: VulkanoWindows, device: Arc<Device>, context: VulkanoContext, camera: Camera) -> Renderer {
+    // cache_dir is where the tile cache spills to once its memory is full, see `TileCache`
+    pub fn new(mut window: VulkanoWindows, device: Arc<Device>, context: VulkanoContext, camera: Camera, cache_dir: Option<PathBuf>) -> Renderer {
     //     println!("{}", std::mem::size_of::<RenderCamera>());
 
         let memory_allocator: GenericMemoryAllocator<Arc<vulkano::memory::allocator::FreeListAllocator>> = StandardMemoryAllocator::new_default(device.clone());
@@ -145,22 +315,23 @@ impl Renderer {
         let vertex_shader = mandelbrot::vs::load(device.clone()).unwrap();
         let fragment_shader = mandelbrot::fs::load(device.clone()).unwrap();
 
-        let render_target_id = 0;
+        let mut queue_family_indices = vec![graphics_queue.queue_family_index()];
+
+        if compute_queue.queue_family_index() != graphics_queue.queue_family_index() {
+            queue_family_indices.push(compute_queue.queue_family_index());
+        }
+
+        let properties = device.physical_device().properties();
+
+        println!("using {} ({:?}), graphics queue family {}, compute queue family {}",
+            properties.device_name,
+            properties.device_type,
+            graphics_queue.queue_family_index(),
+            compute_queue.queue_family_index());
+
         let renderer = window
             .get_primary_renderer_mut()
             .expect("Failed to create renderer");
-
-        let mut usage = ImageUsage::empty();
-        usage = usage.union(ImageUsage::SAMPLED);
-        usage = usage.union(ImageUsage::STORAGE);
-        usage = usage.union(ImageUsage::COLOR_ATTACHMENT);
-        usage = usage.union(ImageUsage::TRANSFER_DST);
-
-        renderer.add_additional_image_view(
-            render_target_id,
-            DEFAULT_IMAGE_FORMAT,
-            usage
-        );
     
         let compute_pipeline = ComputePipeline::new(
             device.clone(),
@@ -171,6 +342,50 @@ impl Renderer {
         )
         .unwrap();
 
+        let reproject_shader = mandelbrot::reproject::load(device.clone()).unwrap();
+
+        let reproject_pipeline = ComputePipeline::new(
+            device.clone(),
+            reproject_shader.entry_point("main").unwrap(),
+            &(),
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        let stats_shader = mandelbrot::stats::load(device.clone()).unwrap();
+
+        let stats_pipeline = ComputePipeline::new(
+            device.clone(),
+            stats_shader.entry_point("main").unwrap(),
+            &(),
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        let subdivide_shader = mandelbrot::subdivide::load(device.clone()).unwrap();
+
+        let subdivide_pipeline = ComputePipeline::new(
+            device.clone(),
+            subdivide_shader.entry_point("main").unwrap(),
+            &(),
+            None,
+            |_| {},
+        )
+        .unwrap();
+
+        let colorize_shader = mandelbrot::colorize::load(device.clone()).unwrap();
+
+        let colorize_pipeline = ComputePipeline::new(
+            device.clone(),
+            colorize_shader.entry_point("main").unwrap(),
+            &(),
+            None,
+            |_| {},
+        )
+        .unwrap();
+
         let render_pass = vulkano::single_pass_renderpass!(
             device.clone(),
             attachments: {
@@ -214,11 +429,69 @@ impl Renderer {
         )
         .unwrap();
 
-        let offset = device.physical_device().properties().min_uniform_buffer_offset_alignment;
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                mipmap_mode: SamplerMipmapMode::Linear,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        let physical_device = device.physical_device();
+        let timestamp_period = physical_device.queue_family_properties()
+            [compute_queue.queue_family_index() as usize]
+            .timestamp_valid_bits
+            .map(|_| physical_device.properties().timestamp_period);
+
+        let histogram = Histogram::new(
+            device.clone(),
+            &memory_allocator,
+            &descriptor_set_allocator,
+        );
+
+        let hud = Hud::new(
+            device.clone(),
+            &memory_allocator,
+            &command_buffer_allocator,
+            &descriptor_set_allocator,
+            graphics_queue.clone(),
+            subpass.clone(),
+        );
+
+        let julia = Julia::new(
+            device.clone(),
+            &memory_allocator,
+            &descriptor_set_allocator,
+            &queue_family_indices,
+            &graphics_pipeline,
+        );
+
+        let minimap = Minimap::new(
+            device.clone(),
+            &memory_allocator,
+            &descriptor_set_allocator,
+            &queue_family_indices,
+            compute_queue.queue_family_index(),
+            &compute_pipeline,
+            &colorize_pipeline,
+            histogram.cdf(),
+            &graphics_pipeline,
+        );
+
+        let cache = TileCache::new(
+            device.clone(),
+            &memory_allocator,
+            &descriptor_set_allocator,
+            compute_queue.queue_family_index(),
+            &compute_pipeline,
+            cache_dir,
+        );
 
         Renderer {
             window,
-            render_target_id,
 
             camera: camera.into(),
 
@@ -227,157 +500,652 @@ impl Renderer {
             descriptor_set_allocator,
 
             device,
-            data_offset: offset.log2(),
 
             compute_queue,
             graphics_queue,
+            queue_family_indices,
 
-            compute_shader,
             vertex_shader,
             fragment_shader,
 
             render_pass,
             compute_pipeline,
+            reproject_pipeline,
+            stats_pipeline,
+            subdivide_pipeline,
+            colorize_pipeline,
             graphics_pipeline,
 
             delta_time: 0.0,
             previous_frame: Instant::now(),
 
-            vertex_buffer
-        }
-    }
+            vertex_buffer,
+            sampler,
+
+            target_sets: None,
+            frames: HashMap::new(),
+            timestamp_period,
+
+            hud,
+            show_hud: true,
+            cursor: None,
+            orbit: None,
+
+            julia,
+            show_julia: false,
+
+            minimap,
+            show_minimap: true,
+
+            screenshot_scale: None,
+
+            refine_view: None,
+            tiles: TileQueue::new(),
+            resume_from: None,
 
-    pub fn get_iter(&self) -> u32 {
-        return self.camera.max_iters;
+            subdivide: false,
+            subdivision: None,
+
+            cached: false,
+            cache,
+            compute_key: cache::kernel_key(&format!("{}\n{}", mandelbrot::CS_SOURCE, mandelbrot::SAMPLE_SOURCE)),
+
+            shader_watch: None,
+            formula: Formula::Mandelbrot,
+
+            iterations: IterationLimit::new(),
+            coloring: Coloring::Iterations,
+            interior: Interior::Flat,
+            histogram,
+            pending_stats: None
+        }
     }
 
     pub fn update_view(&mut self, cam: Camera) {
-        let iter = self.camera.max_iters;
+        let zoom = cam.zoom;
         self.camera = cam.into();
-        self.camera.max_iters = iter;
+        self.camera.max_iters = self.iterations.limit(zoom);
+        self.camera.coloring = self.coloring;
+        self.camera.interior = self.interior;
     }
 
-    pub fn get_delta(&self) -> f32 {
-        return self.delta_time;
+    pub fn update_cursor(&mut self, cursor: Option<[f32; 2]>) {
+        self.cursor = cursor;
     }
 
-    pub fn resize(&mut self) {
-        let renderer = self.window.get_primary_renderer_mut().unwrap();
+    pub fn get_cursor(&self) -> Option<[f32; 2]> {
+        return self.cursor;
+    }
 
-        renderer.resize();
+    // saves the next frame to a png, scale above 1 renders it bigger than the window
+    pub fn request_screenshot(&mut self, scale: u32) {
+        self.screenshot_scale = Some(scale);
+    }
+
+    // complex point under a window pixel if it lands on the minimap
+    pub fn minimap_point(&self, pixel: [f32; 2]) -> Option<[f32; 2]> {
+        if !self.show_minimap {
+            return None;
+        }
+
+        minimap::point_at(pixel, self.window_size())
+    }
+
+    // switches between progressive tiles and subdivision, the view starts over. subdivision
+    // fills a border that escapes at the same iteration, which only holds for the connected
+    // mandelbrot set, custom formulas stay on tiles
+    pub fn toggle_subdivide(&mut self) {
+        self.subdivide = !self.subdivide && self.formula == Formula::Mandelbrot;
+        self.cached = false;
+        self.refine_view = None;
+    }
+
+    // switches between progressive tiles and the tile cache, the view starts over
+    pub fn toggle_cache(&mut self) {
+        self.cached = !self.cached;
+        self.subdivide = false;
+        self.refine_view = None;
+    }
+
+    // true while tiles, subdivision passes, cache tiles or statistics are still outstanding,
+    // the frame after this one has work to do
+    pub fn is_refining(&self) -> bool {
+        return self.has_work_left() || self.pending_stats.is_some();
+    }
+
+    // whether the samples of the view are still incomplete
+    fn has_work_left(&self) -> bool {
+        let subdividing = self.subdivide && self.subdivision.as_ref().map_or(false, |subdivision| !subdivision.is_empty());
+
+        !self.tiles.is_empty() || subdividing || (self.cached && self.cache.is_pending())
+    }
+
+    // compiles the glsl files in dir over the built in shaders and keeps watching them
+    pub fn watch_shaders(&mut self, dir: PathBuf) {
+        self.shader_watch = Some(ShaderWatch::new(dir));
+        self.reload_shaders();
+    }
+
+    pub fn watches_shaders(&self) -> bool {
+        self.shader_watch.is_some()
     }
 
-    fn pad(min: u32, original: u32) -> u32 {
-        // Calculate required alignment based on minimum device offset alignment
-        // let minUboAlignment = _gpuProperties.limits.minUniformBufferOffsetAlignment;
+    // swaps in pipelines for the watched files that changed, true if that changes what's on
+    // screen. a pipeline that can't be built keeps the old one
+    pub fn reload_shaders(&mut self) -> bool {
+        let (previous_error, changed) = match &mut self.shader_watch {
+            Some(watch) => (watch.error(), watch.poll(self.device.clone(), &self.formula)),
+            None => return false,
+        };
+
+        let rebuilt = !changed.is_empty();
 
-        let mut aligned_size = original;
-        if min > 0 {
-            aligned_size = (aligned_size + min - 1) & !(min - 1);
+        for (stage, source, module) in changed {
+            match self.rebuild_pipeline(stage, module) {
+                // cached tiles of the old kernel don't match anymore
+                Ok(()) if stage == Stage::Compute => {
+                    self.compute_key = cache::kernel_key(&source);
+                    self.minimap.invalidate();
+                }
+                Ok(()) => (),
+                Err(e) => self.shader_watch.as_mut().unwrap().fail(stage, e),
+            }
         }
-        return aligned_size; 
+
+        rebuilt || self.shader_watch.as_ref().unwrap().error() != previous_error
     }
 
-    pub fn render(&mut self) {
-        let renderer = self.window.get_primary_renderer_mut().unwrap();
+    pub fn formula(&self) -> &Formula {
+        &self.formula
+    }
+
+    // rebuilds the `cs` and `subdivide` kernels around another formula, the view starts
+    // over. nothing changes unless both build
+    pub fn set_formula(&mut self, formula: Formula) -> Result<(), String> {
+        let mut compiled = Vec::new();
+
+        for stage in [Stage::Compute, Stage::Subdivide] {
+            // watched files as they are now, the next poll has nothing new to compile
+            let (source, sample) = match &self.shader_watch {
+                Some(watch) => (watch.source(stage)?, watch.sample()?),
+                None => (stage.builtin().to_string(), mandelbrot::SAMPLE_SOURCE.to_string()),
+            };
+
+            compiled.push(shader_watch::compile_stage(stage, &source, &sample, &formula, self.device.clone())?);
+        }
+
+        let (_, subdivide_module) = compiled.pop().unwrap();
+        let (compute_source, compute_module) = compiled.pop().unwrap();
+
+        let compute = compute_pipeline(&self.device, Stage::Compute, &compute_module, &self.compute_pipeline)?;
+        let subdivide = compute_pipeline(&self.device, Stage::Subdivide, &subdivide_module, &self.subdivide_pipeline)?;
+
+        // both built, nothing can fail from here on
+        self.compute_pipeline = compute;
+        self.subdivide_pipeline = subdivide;
+        self.compute_key = cache::kernel_key(&compute_source);
+
+        self.subdivide = self.subdivide && formula == Formula::Mandelbrot;
+        self.formula = formula;
+        self.refine_view = None;
+        self.minimap.invalidate();
+
+        Ok(())
+    }
+
+    // the new shader goes into the pipeline's existing layout, so every descriptor set made
+    // for the old one still fits
+    fn rebuild_pipeline(&mut self, stage: Stage, module: Arc<ShaderModule>) -> Result<(), String> {
+        if module.entry_point("main").is_none() {
+            return Err(format!("{:?} shader has no main", stage));
+        }
+
+        match stage {
+            Stage::Compute => {
+                self.compute_pipeline = compute_pipeline(&self.device, stage, &module, &self.compute_pipeline)?;
+
+                // samples of the old kernel don't count
+                self.refine_view = None;
+            }
+            Stage::Subdivide => {
+                self.subdivide_pipeline = compute_pipeline(&self.device, stage, &module, &self.subdivide_pipeline)?;
+
+                self.refine_view = None;
+            }
+            Stage::Colorize => {
+                self.colorize_pipeline = compute_pipeline(&self.device, stage, &module, &self.colorize_pipeline)?;
+            }
+            Stage::Vertex | Stage::Fragment => {
+                let (vertex_shader, fragment_shader) = match stage {
+                    Stage::Vertex => (module, self.fragment_shader.clone()),
+                    _ => (self.vertex_shader.clone(), module),
+                };
+
+                self.graphics_pipeline = GraphicsPipeline::start()
+                    .render_pass(Subpass::from(self.render_pass.clone(), 0).unwrap())
+                    .vertex_shader(vertex_shader.entry_point("main").unwrap(), ())
+                    .fragment_shader(fragment_shader.entry_point("main").unwrap(), ())
+                    .input_assembly_state(Default::default())
+                    .vertex_input_state(Vertex::per_vertex())
+                    .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+                    .with_pipeline_layout(self.device.clone(), self.graphics_pipeline.layout().clone())
+                    .map_err(|e| format!("graphics pipeline: {}", e))?;
+
+                self.vertex_shader = vertex_shader;
+                self.fragment_shader = fragment_shader;
+            }
+        }
+
+        Ok(())
+    }
 
-        let now = Instant::now();
-        let dt = now - self.previous_frame;
-        self.delta_time = dt.as_secs_f32();
+    pub fn window(&self) -> &Window {
+        self.window.get_primary_window().unwrap()
+    }
 
-        //todo:do something with this
-        let _fps = 1.0 / self.delta_time;
+    pub fn window_size(&self) -> [f32; 2] {
+        self.window.get_primary_renderer().unwrap().window_size()
+    }
+
+    pub fn resize(&mut self) {
+        let renderer = self.window.get_primary_renderer_mut().unwrap();
 
+        renderer.resize();
+    }
 
-        self.previous_frame = now;
+    // returns false when no frame could be presented
+    pub fn render(&mut self) -> bool {
+        let renderer = self.window.get_primary_renderer_mut().unwrap();
 
         let dimensions = renderer.window_size();
         let width = dimensions[0];
         let height = dimensions[1];
 
+        // minimized, nothing to draw into
         if width == 0.0 || height == 0.0 {
-            return;
+            return false;
         }
 
+        // frames only happen when something changed, so the time between them says
+        // nothing. delta_time is the cpu time spent recording and submitting this frame
+        self.previous_frame = Instant::now();
+
         let acquire_future = match renderer.acquire() {
             Ok(future) => future,
             Err(e) => {
                 eprintln!("{}", e);
-                return;
+
+                // the swapchain gets recreated on the next acquire, try again right away
+                renderer.window().request_redraw();
+                return false;
             }
         };
 
-        let image_view = renderer.get_additional_image_view(self.render_target_id);
+        // the render target follows the swapchain size
+        let swapchain_size = renderer.swapchain_image_size();
+
+        let stale_sets = match &self.target_sets {
+            Some(sets) => sets.dims() != swapchain_size,
+            None => true,
+        };
+
+        if stale_sets {
+            self.target_sets = Some(TargetSets::new(
+                &self.memory_allocator,
+                &self.descriptor_set_allocator,
+                swapchain_size,
+                &self.queue_family_indices,
+                self.compute_queue.queue_family_index(),
+                self.device.physical_device().properties().max_storage_buffer_range,
+                self.sampler.clone(),
+                self.histogram.cdf(),
+                &self.compute_pipeline,
+                &self.reproject_pipeline,
+                &self.colorize_pipeline,
+                &self.graphics_pipeline,
+            ));
+        }
+
+        let sets = self.target_sets.as_ref().unwrap();
+        let image_view = sets.target.clone();
+
+        // the swapchain images only change when the swapchain is recreated
+        let image_index = renderer.image_index();
+        let swapchain_view = renderer.swapchain_image_view();
+
+        let stale_frame = match self.frames.get(&image_index) {
+            Some(frame) => !Arc::ptr_eq(&frame.image, &swapchain_view),
+            None => true,
+        };
+
+        if stale_frame {
+            self.frames.insert(image_index, FrameSlot::new(
+                self.device.clone(),
+                self.render_pass.clone(),
+                swapchain_view,
+                self.timestamp_period.is_some(),
+            ));
+        }
+
+        let frame = self.frames.get_mut(&image_index).unwrap();
+
+        // the last frame that used this slot has been waited on by now, its timestamps
+        // size the next batch. results that aren't in yet are skipped
+        if let (Some(timestamps), Some(period), true) = (&frame.timestamps, self.timestamp_period, frame.timed) {
+            let mut ticks = [0u64; 2];
+
+            if let Ok(true) = timestamps
+                .queries_range(0..2)
+                .unwrap()
+                .get_results(&mut ticks, QueryResultFlags::empty())
+            {
+                let seconds = ticks[1].saturating_sub(ticks[0]) as f32 * period / 1e9;
+
+                match (&mut self.subdivision, self.subdivide) {
+                    (Some(subdivision), true) => subdivision.batch_took(seconds),
+                    _ => self.tiles.batch_took(seconds),
+                }
+            }
+        }
+
+        frame.timed = false;
 
         let mut compute_command_buffer_builder = AutoCommandBufferBuilder::primary(
             &self.command_buffer_allocator,
-            self.graphics_queue.queue_family_index(),
+            self.compute_queue.queue_family_index(),
             CommandBufferUsage::OneTimeSubmit,
         )
         .unwrap();
 
-        let set_layout = self.compute_pipeline.layout().set_layouts().get(0).unwrap();
+        let img_dims = image_view.image().dimensions().width_height();
 
-        let set = PersistentDescriptorSet::new(
-            &self.descriptor_set_allocator,
-            set_layout.clone(),
-            [WriteDescriptorSet::image_view(0, image_view.clone())],
-        )
-        .unwrap();
+        // the last finished view may move the automatic limit, the buffer can't be read
+        // while the gpu still has it
+        let mut stats_read = false;
 
-        let img_dims = image_view.image().dimensions().width_height();
+        if let Some((iters, buffer)) = &self.pending_stats {
+            if let Ok(stats) = buffer.read() {
+                stats_read = true;
+
+                // stale if the limit was changed by hand since
+                if *iters == self.camera.max_iters && self.iterations.feed(&stats, *iters) {
+                    self.camera.max_iters = self.iterations.limit(self.camera.zoom);
+                }
+            }
+        }
+
+        if stats_read {
+            self.pending_stats = None;
+        }
 
         let render_data = self.camera.clone();
 
-        let push_constants = mandelbrot::cs::PushConstants {
-            scale: render_data.zoom.into(),
-            translation: render_data.translation,
-            max_iters: render_data.max_iters,
+        // progressive refinement, a coarse pass right after the view changes and finer ones
+        // after it until every pixel has its own sample. the passes are cut into tiles that
+        // go out a few per frame, a view change cancels whatever is left
+        let same_view = match &self.refine_view {
+            Some((camera, target)) => camera.same_samples(&render_data)
+                && Arc::ptr_eq(target, &image_view)
+                // subdivision may have filled in what the new coloring needs per pixel
+                && (!self.subdivide || subdivide::fills(camera) == subdivide::fills(&render_data)),
+            None => false,
         };
 
-        compute_command_buffer_builder
-            .bind_pipeline_compute(self.compute_pipeline.clone())
-            .bind_descriptor_sets(
-                PipelineBindPoint::Compute,
-                self.compute_pipeline.layout().clone(),
-                0,
-                set,
+        // the cache stops at its finest level, views deeper than that use the tiles
+        let cached = self.cached && cache::covers(&render_data, img_dims);
+
+        if self.cached && !cached {
+            self.cache.cancel();
+        }
+
+        // only the limit went up, the passes go over the same samples again and carry on
+        // the orbits that ran into the old one. subdivision and the cache keep no orbits
+        let resumes = match &self.refine_view {
+            Some((camera, target)) => !same_view
+                && sets.resumable
+                && !self.subdivide
+                && !cached
+                && Arc::ptr_eq(target, &image_view)
+                && camera.resumes_at(&render_data),
+            None => false,
+        };
+
+        // set when this frame completes the view
+        let mut finished = false;
+
+        if resumes {
+            let previous_iters = self.refine_view.as_ref().unwrap().0.max_iters;
+
+            // pixels an unfinished resume hasn't reached yet are still at its lower limit
+            let floor = match self.resume_from {
+                Some(floor) if !self.tiles.is_empty() => floor,
+                _ => previous_iters,
+            };
+
+            self.tiles.restart(img_dims);
+            self.refine_view = Some((render_data.clone(), image_view.clone()));
+            self.resume_from = Some(floor);
+        } else if !same_view {
+            // a new view of the same target starts from the last frame's pixels instead of nothing
+            match &self.refine_view {
+                Some((camera, target)) if Arc::ptr_eq(target, &image_view) => {
+                    reproject::record(
+                        &mut compute_command_buffer_builder,
+                        self.reproject_pipeline.clone(),
+                        sets,
+                        camera,
+                        &render_data,
+                    );
+                }
+                _ => {
+                    // fresh target, no pixel is exact yet
+                    compute_command_buffer_builder
+                        .clear_color_image(ClearColorImageInfo::image(sets.samples.image().clone()))
+                        .unwrap();
+                }
+            }
+
+            if self.subdivide {
+                let stale = match &self.subdivision {
+                    Some(subdivision) => !Arc::ptr_eq(&subdivision.target, &sets.samples),
+                    None => true,
+                };
+
+                if stale {
+                    self.subdivision = Some(Subdivision::new(
+                        &self.memory_allocator,
+                        &self.descriptor_set_allocator,
+                        &self.subdivide_pipeline,
+                        sets.samples.clone(),
+                        sets.orbits.clone(),
+                    ));
+                }
+
+                // the passes go out a chunk at a time like tiles do, see below
+                self.subdivision.as_mut().unwrap().restart();
+                self.tiles.cancel();
+            } else if cached {
+                // the cache composes its tiles over those pixels instead
+                self.tiles.cancel();
+            } else {
+                self.tiles.restart(img_dims);
+            }
+
+            self.refine_view = Some((render_data.clone(), image_view.clone()));
+            self.resume_from = None;
+        }
+
+        let batch = self.tiles.next_batch();
+
+        let chunks = match (&mut self.subdivision, self.subdivide) {
+            (Some(subdivision), true) => subdivision.next_batch(),
+            _ => Vec::new(),
+        };
+
+        let caching = cached && (!same_view || self.cache.is_pending());
+
+        // the cache's work is timed with the tiles and sized by their budget, the two never
+        // run in the same frame
+        if !batch.is_empty() || !chunks.is_empty() || caching {
+            if let Some(timestamps) = &frame.timestamps {
+                unsafe {
+                    compute_command_buffer_builder
+                        .reset_query_pool(timestamps.clone(), 0..2)
+                        .unwrap()
+                        .write_timestamp(timestamps.clone(), 0, PipelineStage::TopOfPipe)
+                        .unwrap();
+                }
+
+                frame.timed = true;
+            }
+        }
+
+        // tiles already cached come in right away, missing ones are computed a few per frame
+        if caching {
+            let was_pending = self.cache.is_pending();
+
+            let complete = self.cache.record(
+                &mut compute_command_buffer_builder,
+                &self.memory_allocator,
+                &self.descriptor_set_allocator,
+                self.compute_pipeline.clone(),
+                sets,
+                &render_data,
+                self.compute_key,
+                self.tiles.per_frame(),
+            );
+
+            finished = complete && (was_pending || !same_view);
+        }
+
+        if !batch.is_empty() {
+            compute_command_buffer_builder
+                .bind_pipeline_compute(self.compute_pipeline.clone())
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Compute,
+                    self.compute_pipeline.layout().clone(),
+                    0,
+                    sets.compute.clone(),
+                );
+        }
+
+        for tile in &batch {
+            let mut push_constants = render_data.push_constants(tile.block, true, tile.block_offset());
+            push_constants.resume_from = self.resume_from.unwrap_or(0);
+
+            compute_command_buffer_builder
+                .push_constants(self.compute_pipeline.layout().clone(), 0, push_constants)
+                .dispatch(tile.workgroups())
+                .expect("err er re r re  ");
+        }
+
+        if let (Some(subdivision), false) = (&self.subdivision, chunks.is_empty()) {
+            subdivision.record(
+                &mut compute_command_buffer_builder,
+                self.subdivide_pipeline.clone(),
+                &render_data,
+                &chunks,
+            );
+
+            finished |= subdivision.is_empty();
+        }
+
+        finished |= !batch.is_empty() && self.tiles.is_empty();
+
+        // the view just finished, measure it for the automatic limit
+        if finished && self.iterations.auto {
+            let buffer = iterations::record_stats(
+                &mut compute_command_buffer_builder,
+                &self.memory_allocator,
+                &self.descriptor_set_allocator,
+                self.stats_pipeline.clone(),
+                sets.samples.clone(),
+                render_data.max_iters,
+            );
+
+            self.pending_stats = Some((render_data.max_iters, buffer));
+        }
+
+        if frame.timed {
+            if let Some(timestamps) = &frame.timestamps {
+                unsafe {
+                    compute_command_buffer_builder
+                        .write_timestamp(timestamps.clone(), 1, PipelineStage::BottomOfPipe)
+                        .unwrap();
+                }
+            }
+        }
+
+        // whatever the samples hold now gets colored, which is all a coloring change needs
+        if render_data.coloring == Coloring::Histogram {
+            self.histogram.record(
+                &mut compute_command_buffer_builder,
+                &self.descriptor_set_allocator,
+                sets.samples.clone(),
+                render_data.max_iters,
+            );
+        }
+
+        colorize::record(
+            &mut compute_command_buffer_builder,
+            self.colorize_pipeline.clone(),
+            sets.colorize.clone(),
+            &render_data,
+            img_dims,
+            self.resume_from.unwrap_or(0),
+        );
+
+        // the window's target holds the whole view once nothing is left for later frames.
+        // the same as `has_work_left`, spelled out since the frame borrows self
+        let subdividing = self.subdivide && self.subdivision.as_ref().map_or(false, |subdivision| !subdivision.is_empty());
+        let complete = self.tiles.is_empty() && !subdividing && !(self.cached && self.cache.is_pending());
+
+        let capture = self.screenshot_scale.take().map(|scale| {
+            screenshot::record(
+                &mut compute_command_buffer_builder,
+                &self.memory_allocator,
+                &self.descriptor_set_allocator,
+                self.compute_pipeline.clone(),
+                self.colorize_pipeline.clone(),
+                &mut self.histogram,
+                image_view.image().clone(),
+                self.compute_queue.queue_family_index(),
+                &render_data,
+                dimensions,
+                scale,
+                self.device.physical_device().properties().max_image_dimension2_d,
+                complete,
             )
-            .push_constants(self.compute_pipeline.layout().clone(), 0, push_constants)
-            .dispatch([img_dims[0] / 8, img_dims[1] / 8, 1])
-            .expect("err er re r re  ");
+        });
+
+        if self.show_minimap {
+            self.minimap.dispatch(
+                &mut compute_command_buffer_builder,
+                self.compute_pipeline.clone(),
+                self.colorize_pipeline.clone(),
+            );
+        }
 
-        let compute_command_buffer = compute_command_buffer_builder.build().unwrap();
+        // julia inset follows the cursor, the last one stays up when it leaves the window.
+        // its kernel only knows z² + c, so it's hidden while a custom formula is active
+        if self.show_julia && self.formula == Formula::Mandelbrot {
+            if let Some(cursor) = self.cursor {
+                let c = render_data.to_complex(cursor, dimensions);
 
-        let compute_future = compute_command_buffer
-            .execute(self.graphics_queue.clone())
-            .unwrap()
-            .then_signal_fence_and_flush()
-            .unwrap()
-            .join(acquire_future);
+                self.julia.dispatch(&mut compute_command_buffer_builder, c, render_data.max_iters);
+            }
+        }
 
-        let sampler = Sampler::new(
-            self.device.clone(),
-            SamplerCreateInfo {
-                mag_filter: Filter::Linear,
-                min_filter: Filter::Linear,
-                mipmap_mode: SamplerMipmapMode::Linear,
-                ..Default::default()
-            },
-        )
-        .unwrap();
+        let compute_command_buffer = compute_command_buffer_builder.build().unwrap();
 
-        let set_layout = self.graphics_pipeline.layout().set_layouts().get(0).unwrap();
-        let set = PersistentDescriptorSet::new(
-            &self.descriptor_set_allocator,
-            set_layout.clone(),
-            [WriteDescriptorSet::image_view_sampler(
-                0,
-                image_view.clone(),
-                sampler.clone(),
-            )],
-        )
-        .unwrap();
+        // no fence here, the compute work is chained onto the previous frame and the
+        // graphics work after it so the cpu can go on to record the next frame. the
+        // semaphore hands the images over to the graphics queue
+        let compute_future = acquire_future
+            .then_execute(self.compute_queue.clone(), compute_command_buffer)
+            .unwrap()
+            .then_signal_semaphore_and_flush()
+            .unwrap();
 
         let mut graphics_command_buffer_builder = AutoCommandBufferBuilder::primary(
             &self.command_buffer_allocator,
@@ -386,17 +1154,6 @@ impl Renderer {
         )
         .unwrap();
 
-        let target = renderer.swapchain_image_view();
-
-        let framebuffer = Framebuffer::new(
-            self.render_pass.clone(),
-            FramebufferCreateInfo {
-                attachments: vec![target],
-                ..Default::default()
-            },
-        )
-        .unwrap();
-
         let viewport = Viewport {
             origin: [0.0, 0.0],
             dimensions: renderer.window_size(),
@@ -409,7 +1166,7 @@ impl Renderer {
                 RenderPassBeginInfo {
                     render_pass: self.render_pass.clone(),
                     clear_values: vec![Some([0.3, 0.3, 0.3, 1.0].into())],
-                    ..RenderPassBeginInfo::framebuffer(framebuffer.clone())
+                    ..RenderPassBeginInfo::framebuffer(frame.framebuffer.clone())
                 },
                 SubpassContents::Inline,
             )
@@ -418,12 +1175,78 @@ impl Renderer {
                 PipelineBindPoint::Graphics,
                 self.graphics_pipeline.layout().clone(),
                 0,
-                set,
+                sets.graphics.clone(),
             )
             .set_viewport(0, [viewport])
             .bind_vertex_buffers(0, self.vertex_buffer.clone())
             .draw(3 as u32, 1, 0, 0)
-            .unwrap()
+            .unwrap();
+
+        let mut overlay = Vec::new();
+
+        if self.show_julia && self.formula == Formula::Mandelbrot && self.julia.is_rendered() {
+            self.julia.draw(
+                &mut graphics_command_buffer_builder,
+                &self.memory_allocator,
+                self.graphics_pipeline.clone(),
+                dimensions,
+            );
+
+            if let Some((min, max)) = julia::inset_rect(dimensions) {
+                overlay.extend(hud::layout_frame(min, max, dimensions));
+            }
+        }
+
+        if self.show_minimap && self.minimap.is_rendered() {
+            self.minimap.draw(
+                &mut graphics_command_buffer_builder,
+                &self.memory_allocator,
+                self.graphics_pipeline.clone(),
+                dimensions,
+            );
+
+            if let (Some((min, max)), Some(marker)) = (minimap::minimap_rect(dimensions), minimap::marker(&self.camera, dimensions)) {
+                overlay.extend(hud::layout_frame(min, max, dimensions));
+                overlay.extend(hud::layout_marker(&marker, self.camera.zoom, [min[0], min[1]], dimensions));
+            }
+        }
+
+        if let Some(orbit) = &self.orbit {
+            overlay.extend(hud::layout_orbit(orbit, &self.camera, dimensions));
+        }
+
+        let mut lines = Vec::new();
+
+        if self.show_hud {
+            lines = hud::overlay_text(&self.camera, self.iterations.auto, self.delta_time, self.cursor, dimensions);
+
+            if let Formula::Custom(_) = self.formula {
+                lines.push(format!("f(z)   {}", self.formula.name()));
+            }
+
+            if let Some(orbit) = &self.orbit {
+                lines.extend(hud::orbit_text(orbit));
+            }
+
+            overlay.extend(hud::layout_tiles(&self.tiles.pending_tiles(), dimensions));
+        }
+
+        // a broken shader stays up, hud or not, until its file compiles again
+        if let Some(error) = self.shader_watch.as_ref().and_then(|watch| watch.error()) {
+            lines.extend(hud::error_text(&error));
+        }
+
+        if !lines.is_empty() {
+            overlay.extend(hud::layout_text(&lines, hud::hud_origin(), dimensions));
+        }
+
+        self.hud.draw(
+            &mut graphics_command_buffer_builder,
+            &self.memory_allocator,
+            overlay,
+        );
+
+        graphics_command_buffer_builder
             .end_render_pass()
             .unwrap();
 
@@ -434,7 +1257,36 @@ impl Renderer {
             .unwrap()
             .boxed();
 
-        renderer.present(after_future, true);
+        // only a screenshot readback has to wait for the gpu
+        renderer.present(after_future, capture.is_some());
+
+        // keep frames coming until the last tile is done and measured
+        if self.is_refining() {
+            self.window.get_primary_renderer_mut().unwrap().window().request_redraw();
+        }
+
+        self.delta_time = self.previous_frame.elapsed().as_secs_f32();
+
+        // present waited on the frame, so the readback is done
+        if let Some(capture) = capture {
+            match capture.save() {
+                Ok(path) => println!("saved {}", path.display()),
+                Err(e) => eprintln!("failed to save screenshot: {}", e),
+            }
+        }
+
+        true
     }
 
-}
\ No newline at end of file
+}
+// a compute pipeline for the stage's new shader in the layout of the one it replaces, so every
+// descriptor set made for the old one still fits
+fn compute_pipeline(device: &Arc<Device>,
+        stage: Stage,
+        module: &Arc<ShaderModule>,
+        old: &Arc<ComputePipeline>) -> Result<Arc<ComputePipeline>, String> {
+    let entry_point = module.entry_point("main").ok_or(format!("{:?} shader has no main", stage))?;
+
+    ComputePipeline::with_pipeline_layout(device.clone(), entry_point, &(), old.layout().clone(), None)
+        .map_err(|e| format!("{} pipeline: {}", stage.file_name(), e))
+}
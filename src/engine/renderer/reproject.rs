@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CopyImageInfo, PrimaryAutoCommandBuffer};
+use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
+
+use super::frame::TargetSets;
+use super::render::RenderCamera;
+
+use crate::engine::shaders::mandelbrot;
+
+// xaos style reuse of the last frame. the raw results are copied aside and every pixel of
+// the new view picks up the nearest old sample. samples that land exactly on an old one
+// (whole pixel pans) stay exact, the rest are stand ins the progressive passes redo
+pub fn record(builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        pipeline: Arc<ComputePipeline>,
+        sets: &TargetSets,
+        previous_camera: &RenderCamera,
+        camera: &RenderCamera) {
+    builder
+        .copy_image(CopyImageInfo::images(sets.samples.image().clone(), sets.previous_samples.image().clone()))
+        .unwrap()
+        .copy_image(CopyImageInfo::images(sets.orbits.image().clone(), sets.previous_orbits.image().clone()))
+        .unwrap();
+
+    let push_constants = mandelbrot::reproject::PushConstants {
+        scale: camera.zoom.into(),
+        translation: camera.translation,
+        rotation: camera.rotation,
+        previous_scale: previous_camera.zoom,
+        previous_translation: previous_camera.translation,
+        previous_rotation: previous_camera.rotation,
+        same_sampling: camera.same_sampling(previous_camera) as u32,
+    };
+
+    let [width, height] = sets.dims();
+
+    builder
+        .bind_pipeline_compute(pipeline.clone())
+        .bind_descriptor_sets(
+            PipelineBindPoint::Compute,
+            pipeline.layout().clone(),
+            0,
+            sets.reproject.clone(),
+        )
+        .push_constants(pipeline.layout().clone(), 0, push_constants)
+        .dispatch([(width + 7) / 8, (height + 7) / 8, 1])
+        .unwrap();
+}
@@ -0,0 +1,200 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use image::{ImageBuffer, Rgba};
+
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CopyImageToBufferInfo, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::format::Format;
+use vulkano::image::{ImageAccess, ImageUsage, StorageImage};
+use vulkano::image::view::ImageView;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
+
+use super::coloring::Coloring;
+use super::colorize;
+use super::frame::{self, ORBITS_FORMAT, SAMPLES_FORMAT};
+use super::histogram::Histogram;
+use super::render::RenderCamera;
+
+// frame readback waiting for the gpu to finish before it can be written out
+pub struct Capture {
+    buffer: Subbuffer<[u8]>,
+    width: u32,
+    height: u32,
+
+    camera: RenderCamera,
+    // size of the window the view was framed in, the capture may be bigger
+    window_size: [f32; 2]
+}
+
+// largest scale a target can be rendered at, the device can't make images bigger than
+// max_dimension on either side
+pub fn max_scale(target: [u32; 2], max_dimension: u32) -> u32 {
+    (max_dimension / target[0].max(target[1]).max(1)).max(1)
+}
+
+// records a copy of the render target into a host buffer. with a scale above 1, or while
+// the target is still being refined, the fractal is rendered and colored again into
+// offscreen images that many times the window size, see `max_scale`
+pub fn record(builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        memory_allocator: &StandardMemoryAllocator,
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+        compute_pipeline: Arc<ComputePipeline>,
+        colorize_pipeline: Arc<ComputePipeline>,
+        histogram: &mut Histogram,
+        render_target: Arc<dyn ImageAccess>,
+        queue_family_index: u32,
+        camera: &RenderCamera,
+        window_size: [f32; 2],
+        scale: u32,
+        max_dimension: u32,
+        target_complete: bool) -> Capture {
+    let [target_width, target_height] = render_target.dimensions().width_height();
+
+    let max = max_scale([target_width, target_height], max_dimension);
+
+    if scale > max {
+        eprintln!("screenshot at {}x instead of {}x, the device's images go up to {} pixels", max, scale, max_dimension);
+    }
+
+    let scale = scale.min(max);
+
+    let source = if scale > 1 || !target_complete {
+        let dims = [target_width * scale, target_height * scale];
+
+        let image = record_offscreen(
+            builder,
+            memory_allocator,
+            descriptor_set_allocator,
+            compute_pipeline,
+            colorize_pipeline,
+            histogram,
+            queue_family_index,
+            camera,
+            dims,
+        );
+
+        image.image().clone() as Arc<dyn ImageAccess>
+    } else {
+        render_target
+    };
+
+    let [width, height] = source.dimensions().width_height();
+
+    let buffer = Buffer::new_slice::<u8>(
+        memory_allocator,
+        BufferCreateInfo {
+            usage: BufferUsage::TRANSFER_DST,
+            ..Default::default()
+        },
+        AllocationCreateInfo {
+            usage: MemoryUsage::Download,
+            ..Default::default()
+        },
+        width as u64 * height as u64 * 4,
+    )
+    .unwrap();
+
+    builder
+        .copy_image_to_buffer(CopyImageToBufferInfo::image_buffer(source, buffer.clone()))
+        .unwrap();
+
+    Capture {
+        buffer,
+        width,
+        height,
+
+        camera: camera.clone(),
+        window_size
+    }
+}
+
+// records rendering and coloring the camera's view into a fresh rgba8 image of the given
+// size in one go, the image can be copied out once the gpu is done. the camera maps the
+// image like a window of that size
+pub fn record_offscreen(builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        memory_allocator: &StandardMemoryAllocator,
+        descriptor_set_allocator: &StandardDescriptorSetAllocator,
+        compute_pipeline: Arc<ComputePipeline>,
+        colorize_pipeline: Arc<ComputePipeline>,
+        histogram: &mut Histogram,
+        queue_family_index: u32,
+        camera: &RenderCamera,
+        dims: [u32; 2]) -> Arc<ImageView<StorageImage>> {
+    let [width, height] = dims;
+    let families = [queue_family_index];
+
+    let samples = frame::new_image(memory_allocator, dims, SAMPLES_FORMAT, ImageUsage::STORAGE, &families);
+    let orbits = frame::new_image(memory_allocator, dims, ORBITS_FORMAT, ImageUsage::STORAGE, &families);
+    let image = frame::new_image(
+        memory_allocator,
+        dims,
+        Format::R8G8B8A8_UNORM,
+        ImageUsage::STORAGE | ImageUsage::TRANSFER_SRC,
+        &families,
+    );
+
+    let states = frame::new_states(memory_allocator, 1);
+    let set = frame::raw_set(descriptor_set_allocator, &compute_pipeline, &samples, &orbits, states);
+
+    let push_constants = camera.push_constants(1, false, [0, 0]);
+
+    builder
+        .bind_pipeline_compute(compute_pipeline.clone())
+        .bind_descriptor_sets(
+            PipelineBindPoint::Compute,
+            compute_pipeline.layout().clone(),
+            0,
+            set,
+        )
+        .push_constants(compute_pipeline.layout().clone(), 0, push_constants)
+        .dispatch([(width + 7) / 8, (height + 7) / 8, 1])
+        .unwrap();
+
+    // binned on its own, a window's cdf comes from other samples
+    if camera.coloring == Coloring::Histogram {
+        histogram.record(builder, descriptor_set_allocator, samples.clone(), camera.max_iters);
+    }
+
+    let colorize_set = frame::colorize_set(
+        descriptor_set_allocator,
+        &colorize_pipeline,
+        &samples,
+        &orbits,
+        &image,
+        histogram.cdf(),
+    );
+
+    colorize::record(builder, colorize_pipeline, colorize_set, camera, dims, 0);
+
+    image
+}
+
+impl Capture {
+    // writes a timestamped png plus a text file with the view it was taken at,
+    // the gpu has to be done with the copy by now
+    pub fn save(self) -> image::ImageResult<PathBuf> {
+        let pixels = self.buffer.read().unwrap().to_vec();
+
+        let image = ImageBuffer::<Rgba<u8>, _>::from_raw(self.width, self.height, pixels).unwrap();
+
+        let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis();
+        let path = PathBuf::from(format!("mandelbrowser-{}.png", stamp));
+
+        image.save(&path)?;
+
+        let center = self.camera.to_complex([self.window_size[0] / 2.0, self.window_size[1] / 2.0], self.window_size);
+        let info = format!(
+            "center {:+.9} {:+.9}i\nzoom {:e}\nrotation {}\nmax_iters {}\nsize {}x{}\n",
+            center[0], center[1], self.camera.zoom, self.camera.rotation, self.camera.max_iters, self.width, self.height,
+        );
+
+        fs::write(path.with_extension("txt"), info)?;
+
+        Ok(path)
+    }
+}
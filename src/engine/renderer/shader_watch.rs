@@ -0,0 +1,217 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use shaderc::{CompileOptions, Compiler, EnvVersion, ResolvedInclude, ShaderKind, TargetEnv};
+
+use vulkano::device::Device;
+use vulkano::shader::ShaderModule;
+
+use crate::engine::shaders::mandelbrot;
+use crate::engine::view::Formula;
+
+// which pipeline a shader goes into
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Stage {
+    Compute,
+    Subdivide,
+    Colorize,
+    Vertex,
+    Fragment
+}
+
+// names are the ones in shaders/glsl, a directory started as a copy of it works as is
+const FILES: [(&str, Stage, ShaderKind); 5] = [
+    ("mandelbrot.comp", Stage::Compute, ShaderKind::Compute),
+    ("subdivide.comp", Stage::Subdivide, ShaderKind::Compute),
+    ("colorize.comp", Stage::Colorize, ShaderKind::Compute),
+    ("mandelbrot.vert", Stage::Vertex, ShaderKind::Vertex),
+    ("mandelbrot.frag", Stage::Fragment, ShaderKind::Fragment),
+];
+
+// included by the iteration kernels, see `mandelbrot::SAMPLE_SOURCE`
+const SAMPLE_FILE: &str = "sample.glsl";
+
+impl Stage {
+    pub fn file_name(self) -> &'static str {
+        FILES[self.index()].0
+    }
+
+    // the cs and subdivide kernels, they include sample.glsl and iterate the formula
+    pub fn iterates(self) -> bool {
+        self == Stage::Compute || self == Stage::Subdivide
+    }
+
+    // what the binary was built with
+    pub fn builtin(self) -> &'static str {
+        match self {
+            Stage::Compute => mandelbrot::CS_SOURCE,
+            Stage::Subdivide => mandelbrot::SUBDIVIDE_SOURCE,
+            Stage::Colorize => mandelbrot::COLORIZE_SOURCE,
+            Stage::Vertex => mandelbrot::VS_SOURCE,
+            Stage::Fragment => mandelbrot::FS_SOURCE,
+        }
+    }
+
+    fn index(self) -> usize {
+        FILES.iter().position(|(_, stage, _)| *stage == self).unwrap()
+    }
+}
+
+// compiles glsl into a module, the error is the compiler's output. `#include "sample.glsl"`
+// gets sample, anything else can't be included
+pub fn compile(source: &str, name: &str, kind: ShaderKind, device: Arc<Device>, sample: &str) -> Result<Arc<ShaderModule>, String> {
+    let compiler = Compiler::new().expect("failed to initialize the shader compiler");
+
+    let mut options = CompileOptions::new().unwrap();
+    options.set_target_env(TargetEnv::Vulkan, EnvVersion::Vulkan1_0 as u32);
+    options.set_include_callback(|requested, _, _, _| {
+        if requested != SAMPLE_FILE {
+            return Err(format!("only {} can be included", SAMPLE_FILE));
+        }
+
+        Ok(ResolvedInclude {
+            resolved_name: SAMPLE_FILE.to_string(),
+            content: sample.to_string(),
+        })
+    });
+
+    let artifact = compiler
+        .compile_into_spirv(source, kind, name, "main", Some(&options))
+        .map_err(|e| e.to_string())?;
+
+    unsafe { ShaderModule::from_words(device, artifact.as_binary()) }
+        .map_err(|e| format!("{}: {}", name, e))
+}
+
+// compiles a stage, the iteration kernels with the formula spliced into the sample.glsl
+// they include. along with the module comes everything it was compiled from, the stage's
+// source followed by that sample.glsl
+pub fn compile_stage(stage: Stage,
+        source: &str,
+        sample: &str,
+        formula: &Formula,
+        device: Arc<Device>) -> Result<(String, Arc<ShaderModule>), String> {
+    let (_, _, kind) = FILES[stage.index()];
+
+    let sample = if stage.iterates() {
+        formula.specialize(sample).map_err(|e| format!("{}: {}", SAMPLE_FILE, e))?
+    } else {
+        String::new()
+    };
+
+    let module = compile(source, stage.file_name(), kind, device, &sample)?;
+
+    Ok((format!("{}\n{}", source, sample), module))
+}
+
+// glsl files in a directory compiled at runtime whenever they change. a file that doesn't
+// compile keeps its error around and leaves the pipeline it goes into as it was, files that
+// don't exist keep the shader built into the binary. an edited sample.glsl rebuilds both
+// iteration kernels
+pub struct ShaderWatch {
+    pub dir: PathBuf,
+
+    // per file, when it was last compiled
+    modified: [Option<SystemTime>; 5],
+    sample_modified: Option<SystemTime>,
+    // per file, what went wrong with its last version
+    errors: [Option<String>; 5]
+}
+
+impl ShaderWatch {
+    pub fn new(dir: PathBuf) -> ShaderWatch {
+        ShaderWatch {
+            dir,
+
+            modified: [None; 5],
+            sample_modified: None,
+            errors: [None, None, None, None, None]
+        }
+    }
+
+    // the stage's file if it's in the directory, the built in source otherwise
+    pub fn source(&self, stage: Stage) -> Result<String, String> {
+        self.read(stage.file_name(), stage.builtin())
+    }
+
+    // what the iteration kernels include, the same way
+    pub fn sample(&self) -> Result<String, String> {
+        self.read(SAMPLE_FILE, mandelbrot::SAMPLE_SOURCE)
+    }
+
+    fn read(&self, name: &str, builtin: &str) -> Result<String, String> {
+        let path = self.dir.join(name);
+
+        if !path.exists() {
+            return Ok(builtin.to_string());
+        }
+
+        fs::read_to_string(&path).map_err(|e| format!("{}: {}", name, e))
+    }
+
+    fn modified(&self, name: &str) -> Option<SystemTime> {
+        fs::metadata(self.dir.join(name)).and_then(|metadata| metadata.modified()).ok()
+    }
+
+    // modules of the stages whose files changed since the last poll and compiled, along
+    // with what they were compiled from, see `compile_stage`. a file that went away goes
+    // back to the built in source
+    pub fn poll(&mut self, device: Arc<Device>, formula: &Formula) -> Vec<(Stage, String, Arc<ShaderModule>)> {
+        let mut changed = Vec::new();
+
+        let sample_modified = self.modified(SAMPLE_FILE);
+        let sample_changed = sample_modified != self.sample_modified;
+        self.sample_modified = sample_modified;
+
+        for (i, (name, stage, _)) in FILES.iter().enumerate() {
+            let modified = self.modified(name);
+
+            let stale = modified != self.modified[i] || (stage.iterates() && sample_changed);
+
+            self.modified[i] = modified;
+
+            if !stale {
+                continue;
+            }
+
+            let result = self.source(*stage).and_then(|source| {
+                let sample = if stage.iterates() { self.sample()? } else { String::new() };
+
+                compile_stage(*stage, &source, &sample, formula, device.clone())
+            });
+
+            match result {
+                Ok((source, module)) => {
+                    self.errors[i] = None;
+                    changed.push((*stage, source, module));
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    self.errors[i] = Some(e);
+                }
+            }
+        }
+
+        changed
+    }
+
+    // for errors past compiling, e.g. a shader that doesn't fit the pipeline layout
+    pub fn fail(&mut self, stage: Stage, error: String) {
+        eprintln!("{}", error);
+
+        self.errors[stage.index()] = Some(error);
+    }
+
+    // every error still standing, none once all the files are fine again
+    pub fn error(&self) -> Option<String> {
+        let errors: Vec<&str> = self.errors.iter().flatten().map(|e| e.as_str()).collect();
+
+        if errors.is_empty() {
+            None
+        } else {
+            Some(errors.join("\n"))
+        }
+    }
+}
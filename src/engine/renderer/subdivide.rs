@@ -0,0 +1,264 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use vulkano::buffer::{Buffer, BufferCreateInfo, BufferUsage, Subbuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CopyBufferInfo, DispatchIndirectCommand, PrimaryAutoCommandBuffer};
+use vulkano::descriptor_set::allocator::StandardDescriptorSetAllocator;
+use vulkano::descriptor_set::{PersistentDescriptorSet, WriteDescriptorSet};
+use vulkano::image::{ImageAccess, StorageImage};
+use vulkano::image::view::ImageView;
+use vulkano::memory::allocator::{AllocationCreateInfo, MemoryUsage, StandardMemoryAllocator};
+use vulkano::pipeline::{ComputePipeline, Pipeline, PipelineBindPoint};
+
+use super::coloring::Interior;
+use super::render::RenderCamera;
+use super::tiles::{Budget, TILE_SIZE};
+
+use crate::engine::shaders::mandelbrot;
+
+// rectangles the first pass starts from, halved every pass down to MIN_SIZE
+const START_SIZE: u32 = 64;
+const MIN_SIZE: u32 = 4;
+
+// pixels the rectangles of one dispatch cover at most, about a tile's worth of work.
+// must match the kernel
+const CHUNK_PIXELS: u32 = TILE_SIZE * TILE_SIZE;
+
+// u32s of a work list header, the indirect dispatch and the rectangle count
+const HEADER: u64 = 4;
+
+// rectangles of this size one dispatch takes on
+fn chunk_rects(size: u32) -> u32 {
+    (CHUNK_PIXELS / (size * size)).max(1)
+}
+
+// one dispatch, a range of the rectangles of a pass
+#[derive(Clone, Copy)]
+pub struct Chunk {
+    pass: u32,
+    index: u32
+}
+
+// mariani-silver. every rectangle gets its border computed, a border of a single iteration
+// count is filled in and anything else is split in four for the next pass. the passes
+// hand their rectangles on through work lists in gpu memory and run with indirect
+// dispatches, so the cpu never sees how the image splits up. it only knows how many
+// rectangles a pass can have at most, and cuts every pass into chunks that go out a few
+// per frame like tiles do. chunks past the real count end right away
+pub struct Subdivision {
+    // samples image the sets write, see `TargetSets`
+    pub target: Arc<ImageView<StorageImage>>,
+
+    // initial -> a, then a -> b and b -> a alternately
+    sets: [Arc<PersistentDescriptorSet>; 3],
+    initial: Subbuffer<[u32]>,
+    lists: [Subbuffer<[u32]>; 2],
+    // an empty header to reset a list with before it gets filled
+    empty: Subbuffer<[u32]>,
+
+    // chunks left for the current view, in order
+    pending: VecDeque<Chunk>,
+    budget: Budget
+}
+
+impl Subdivision {
+    pub fn new(memory_allocator: &StandardMemoryAllocator,
+            descriptor_set_allocator: &StandardDescriptorSetAllocator,
+            pipeline: &ComputePipeline,
+            target: Arc<ImageView<StorageImage>>,
+            orbits: Arc<ImageView<StorageImage>>) -> Subdivision {
+        let [width, height] = target.image().dimensions().width_height();
+
+        let columns = (width + START_SIZE - 1) / START_SIZE;
+        let rows = (height + START_SIZE - 1) / START_SIZE;
+        let count = columns * rows;
+
+        let mut list = vec![count.min(chunk_rects(START_SIZE)), 1, 1, count];
+
+        for y in 0..rows {
+            for x in 0..columns {
+                list.extend([x * START_SIZE, y * START_SIZE, START_SIZE, 0]);
+            }
+        }
+
+        let initial = Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::STORAGE_BUFFER | BufferUsage::INDIRECT_BUFFER,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            list,
+        )
+        .unwrap();
+
+        // no pass can hand on more rectangles than there are of the smallest size
+        let capacity = ((width + MIN_SIZE - 1) / MIN_SIZE) as u64 * ((height + MIN_SIZE - 1) / MIN_SIZE) as u64;
+
+        let new_list = || {
+            Buffer::new_slice::<u32>(
+                memory_allocator,
+                BufferCreateInfo {
+                    usage: BufferUsage::STORAGE_BUFFER | BufferUsage::INDIRECT_BUFFER | BufferUsage::TRANSFER_DST,
+                    ..Default::default()
+                },
+                AllocationCreateInfo {
+                    usage: MemoryUsage::DeviceOnly,
+                    ..Default::default()
+                },
+                HEADER + capacity * 4,
+            )
+            .unwrap()
+        };
+
+        let lists = [new_list(), new_list()];
+
+        let empty = Buffer::from_iter(
+            memory_allocator,
+            BufferCreateInfo {
+                usage: BufferUsage::TRANSFER_SRC,
+                ..Default::default()
+            },
+            AllocationCreateInfo {
+                usage: MemoryUsage::Upload,
+                ..Default::default()
+            },
+            [0u32, 1, 1, 0],
+        )
+        .unwrap();
+
+        let new_set = |input: &Subbuffer<[u32]>, output: &Subbuffer<[u32]>| {
+            PersistentDescriptorSet::new(
+                descriptor_set_allocator,
+                pipeline.layout().set_layouts().get(0).unwrap().clone(),
+                [
+                    WriteDescriptorSet::image_view(0, target.clone()),
+                    WriteDescriptorSet::image_view(1, orbits.clone()),
+                    WriteDescriptorSet::buffer(2, input.clone()),
+                    WriteDescriptorSet::buffer(3, output.clone()),
+                ],
+            )
+            .unwrap()
+        };
+
+        let sets = [
+            new_set(&initial, &lists[0]),
+            new_set(&lists[0], &lists[1]),
+            new_set(&lists[1], &lists[0]),
+        ];
+
+        Subdivision {
+            target,
+
+            sets,
+            initial,
+            lists,
+            empty,
+
+            pending: VecDeque::new(),
+            budget: Budget::new()
+        }
+    }
+
+    // drops whatever was left and queues every pass for a new view
+    pub fn restart(&mut self) {
+        self.pending.clear();
+
+        let [width, height] = self.target.image().dimensions().width_height();
+
+        let mut size = START_SIZE;
+        let mut pass = 0;
+
+        while size >= MIN_SIZE {
+            let rects = ((width + size - 1) / size) * ((height + size - 1) / size);
+            let chunks = (rects + chunk_rects(size) - 1) / chunk_rects(size);
+
+            for index in 0..chunks {
+                self.pending.push_back(Chunk { pass, index });
+            }
+
+            size /= 2;
+            pass += 1;
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.pending.is_empty();
+    }
+
+    pub fn next_batch(&mut self) -> Vec<Chunk> {
+        let count = self.budget.per_frame().min(self.pending.len());
+
+        self.pending.drain(..count).collect()
+    }
+
+    pub fn batch_took(&mut self, seconds: f32) {
+        self.budget.took(seconds);
+    }
+
+    // records a batch of chunks for the view, the whole target is exact once every pass
+    // has run. the first chunk of a pass empties the list it fills
+    pub fn record(&self,
+            builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+            pipeline: Arc<ComputePipeline>,
+            camera: &RenderCamera,
+            batch: &[Chunk]) {
+        builder.bind_pipeline_compute(pipeline.clone());
+
+        let [fill_outside, fill_inside] = fills(camera);
+
+        for chunk in batch {
+            let (input, output, set) = match chunk.pass {
+                0 => (&self.initial, &self.lists[0], &self.sets[0]),
+                n if n % 2 == 1 => (&self.lists[0], &self.lists[1], &self.sets[1]),
+                _ => (&self.lists[1], &self.lists[0], &self.sets[2]),
+            };
+
+            let rects = chunk_rects(START_SIZE >> chunk.pass);
+
+            let push_constants = mandelbrot::subdivide::PushConstants {
+                scale: camera.zoom.into(),
+                translation: camera.translation,
+                max_iters: camera.max_iters,
+                rotation: camera.rotation,
+                min_size: MIN_SIZE,
+                supersample: camera.coloring.supersamples() as u32,
+                fill_outside: fill_outside as u32,
+                fill_inside: fill_inside as u32,
+                chunk_offset: chunk.index * rects,
+                chunk_rects: rects,
+            };
+
+            if chunk.index == 0 {
+                builder
+                    .copy_buffer(CopyBufferInfo::buffers(self.empty.clone(), output.clone().slice(0..HEADER)))
+                    .unwrap();
+            }
+
+            builder
+                .bind_descriptor_sets(
+                    PipelineBindPoint::Compute,
+                    pipeline.layout().clone(),
+                    0,
+                    set.clone(),
+                )
+                .push_constants(pipeline.layout().clone(), 0, push_constants)
+                .dispatch_indirect(input.clone().slice(0..3).reinterpret::<[DispatchIndirectCommand]>())
+                .unwrap();
+        }
+    }
+}
+
+// whether rectangles of one iteration count get filled in outside and inside the set. the
+// coloring decides, a filled view has to be subdivided again when it changes to one that
+// needs every pixel
+pub fn fills(camera: &RenderCamera) -> [bool; 2] {
+    [camera.coloring.by_count(), camera.interior == Interior::Flat]
+}
@@ -0,0 +1,143 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::render::COARSE_BLOCK;
+
+// side length of a tile in pixels, a multiple of 8 * COARSE_BLOCK so every pass
+// dispatches whole workgroups
+pub const TILE_SIZE: u32 = 128;
+
+// gpu time the compute work of one frame should stay under, in seconds
+const COMPUTE_BUDGET: f32 = 0.012;
+const MAX_TILES_PER_FRAME: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Tile {
+    // block size of the progressive pass this tile belongs to
+    pub block: u32,
+    // top left corner in pixels
+    pub origin: [u32; 2],
+    pub size: u32
+}
+
+impl Tile {
+    // number of 8x8 workgroups covering the tile at its block size
+    pub fn workgroups(&self) -> [u32; 3] {
+        let blocks = self.size / self.block;
+
+        [(blocks + 7) / 8, (blocks + 7) / 8, 1]
+    }
+
+    // tile origin in blocks, where the kernel's invocation ids start
+    pub fn block_offset(&self) -> [u32; 2] {
+        [self.origin[0] / self.block, self.origin[1] / self.block]
+    }
+}
+
+// how many pieces of compute work go out per frame, so a single submission never runs
+// long enough to stall the ui or trip the gpu watchdog. pieces should take about as long
+// as a tile
+pub struct Budget {
+    per_frame: usize
+}
+
+impl Budget {
+    pub fn new() -> Budget {
+        Budget { per_frame: 16 }
+    }
+
+    pub fn per_frame(&self) -> usize {
+        self.per_frame
+    }
+
+    // adapts to how long the last batch took on the gpu
+    pub fn took(&mut self, seconds: f32) {
+        if seconds > COMPUTE_BUDGET {
+            self.per_frame = (self.per_frame / 2).max(1);
+        } else if seconds < COMPUTE_BUDGET / 2.0 {
+            self.per_frame = (self.per_frame * 2).min(MAX_TILES_PER_FRAME);
+        }
+    }
+}
+
+// compute work left for the current view, tiles go out a few per frame under a `Budget`
+pub struct TileQueue {
+    pending: VecDeque<Tile>,
+    budget: Budget
+}
+
+impl TileQueue {
+    pub fn new() -> TileQueue {
+        TileQueue {
+            pending: VecDeque::new(),
+            budget: Budget::new()
+        }
+    }
+
+    // drops whatever was left and queues every pass for an image of this size,
+    // coarse passes first and tiles near the middle first within a pass
+    pub fn restart(&mut self, dims: [u32; 2]) {
+        self.pending.clear();
+
+        let mut origins = Vec::new();
+
+        for y in (0..dims[1]).step_by(TILE_SIZE as usize) {
+            for x in (0..dims[0]).step_by(TILE_SIZE as usize) {
+                origins.push([x, y]);
+            }
+        }
+
+        let middle = [dims[0] as f32 / 2.0, dims[1] as f32 / 2.0];
+        let distance = |o: &[u32; 2]| {
+            let dx = o[0] as f32 + TILE_SIZE as f32 / 2.0 - middle[0];
+            let dy = o[1] as f32 + TILE_SIZE as f32 / 2.0 - middle[1];
+
+            dx * dx + dy * dy
+        };
+
+        origins.sort_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap());
+
+        let mut block = COARSE_BLOCK;
+
+        while block >= 1 {
+            for origin in &origins {
+                self.pending.push_back(Tile { block, origin: *origin, size: TILE_SIZE });
+            }
+
+            block /= 2;
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn next_batch(&mut self) -> Vec<Tile> {
+        let count = self.budget.per_frame().min(self.pending.len());
+
+        self.pending.drain(..count).collect()
+    }
+
+    pub fn batch_took(&mut self, seconds: f32) {
+        self.budget.took(seconds);
+    }
+
+    // pieces the next batch may have, other work timed with the tiles goes by it too
+    pub fn per_frame(&self) -> usize {
+        self.budget.per_frame()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.pending.is_empty();
+    }
+
+    // top left corner and size of every tile with work left, each listed once
+    pub fn pending_tiles(&self) -> Vec<([u32; 2], u32)> {
+        let mut seen = HashSet::new();
+
+        self.pending
+            .iter()
+            .filter(|tile| seen.insert(tile.origin))
+            .map(|tile| (tile.origin, tile.size))
+            .collect()
+    }
+}
@@ -0,0 +1,88 @@
+// composes the view out of quadtree tiles resident in the cache atlas, see `renderer::cache`
+
+pub mod compose {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+            layout(binding = 0, set = 0, rgba32f) uniform readonly image2D atlas_samples;
+            layout(binding = 1, set = 0, rgba16f) uniform readonly image2D atlas_orbits;
+            layout(binding = 2, set = 0, rgba32f) uniform writeonly image2D samples;
+            layout(binding = 3, set = 0, rgba16f) uniform writeonly image2D orbits;
+
+            // atlas slot of every tile of the grid covering the view, row by row, -1 for
+            // tiles that aren't in yet
+            layout(binding = 4, set = 0) readonly buffer Slots {
+                int slots[];
+            };
+
+            layout(push_constant) uniform PushConstants {
+                vec2 translation;
+                // complex coordinate of the lower corner of the grid's first tile
+                vec2 grid_origin;
+                uvec2 grid_size;
+                float scale;
+                float rotation;
+                // side of a tile in the complex plane
+                float tile_side;
+                uint atlas_columns;
+            } push_constants;
+
+            // must match `TILE_PIXELS`
+            const int TILE_PIXELS = 128;
+
+            // same mapping as the `cs` kernel
+            vec2 to_complex(vec2 pixel, vec2 dims) {
+                float scale = push_constants.scale;
+                float ar = dims.x / dims.y;
+                vec2 norm = pixel / dims;
+
+                vec2 p = vec2(ar * (norm.x * 4.0 / scale) - (2.0 / scale), (norm.y * 4.0 / scale) - (2.0 / scale));
+                vec2 mid = vec2(ar * (2.0 / scale) - (2.0 / scale), 0.0);
+
+                float s = sin(push_constants.rotation);
+                float co = cos(push_constants.rotation);
+                vec2 d = p - mid;
+
+                return mid + vec2(co * d.x - s * d.y, s * d.x + co * d.y) + push_constants.translation;
+            }
+
+            void main() {
+                ivec2 size = imageSize(samples);
+                ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+
+                if (pixel.x >= size.x || pixel.y >= size.y) {
+                    return;
+                }
+
+                vec2 c = to_complex(vec2(pixel), vec2(size));
+                vec2 cell = floor((c - push_constants.grid_origin) / push_constants.tile_side);
+
+                if (any(lessThan(cell, vec2(0.0))) || any(greaterThanEqual(cell, vec2(push_constants.grid_size)))) {
+                    return;
+                }
+
+                int slot = slots[uint(cell.y) * push_constants.grid_size.x + uint(cell.x)];
+
+                // missing, whatever the pixel held stays as a stand in
+                if (slot < 0) {
+                    return;
+                }
+
+                // nearest sample of the tile, tile pixel k sits at k / TILE_PIXELS of the side
+                vec2 local = ((c - push_constants.grid_origin) / push_constants.tile_side - cell) * float(TILE_PIXELS);
+                ivec2 texel = clamp(ivec2(floor(local + 0.5)), ivec2(0), ivec2(TILE_PIXELS - 1));
+
+                ivec2 corner = ivec2(slot % int(push_constants.atlas_columns), slot / int(push_constants.atlas_columns)) * TILE_PIXELS;
+
+                // the tile is at least as fine as the view, its samples count as exact
+                vec4 value = imageLoad(atlas_samples, corner + texel);
+
+                imageStore(samples, pixel, vec4(value.rgb, 1.0));
+                imageStore(orbits, pixel, imageLoad(atlas_orbits, corner + texel));
+            }
+        ",
+    }
+}
@@ -0,0 +1,12 @@
+#version 460
+
+layout(location = 0) in vec2 tex_coords;
+
+layout(location = 0) out vec4 f_color;
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+
+void main() {
+    // alpha in the storage images is bookkeeping, not coverage
+    f_color = vec4(texture(tex, tex_coords).rgb, 1.0);
+}
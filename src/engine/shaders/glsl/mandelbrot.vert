@@ -0,0 +1,11 @@
+#version 460
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 tex_coords;
+
+layout(location = 0) out vec2 f_tex_coords;
+
+void main() {
+    gl_Position = vec4(position.xy, 0.0, 1.0);
+    f_tex_coords = tex_coords;;
+}
@@ -0,0 +1,196 @@
+// sampling shared by the `cs` and `subdivide` kernels, both include it right after
+// their push constants. it needs scale, translation, max_iters, rotation and
+// supersample in push_constants and the samples and orbits images to store into
+
+// pixel size in the complex plane, what the distance estimate is measured in
+float pixel_size(vec2 dims) {
+    return 4.0 / (push_constants.scale * dims.y);
+}
+
+// complex coordinate of a (possibly fractional) pixel
+vec2 to_complex(vec2 pixel, vec2 dims) {
+    float ar = dims.x / dims.y;
+    float x_norm = (pixel.x / dims.x);
+    float y_norm = (pixel.y / dims.y);
+
+    vec2 p = vec2(
+        ar * (x_norm * 4.0 / push_constants.scale) - (2.0 / push_constants.scale),
+        (y_norm * 4.0 / push_constants.scale) - (2.0 / push_constants.scale)
+    );
+
+    // rotate around the middle of the image
+    vec2 mid = vec2(ar * (2.0 / push_constants.scale) - (2.0 / push_constants.scale), 0.0);
+    float s = sin(push_constants.rotation);
+    float co = cos(push_constants.rotation);
+    vec2 d = p - mid;
+    p = mid + vec2(co * d.x - s * d.y, s * d.x + co * d.y);
+
+    return p + push_constants.translation;
+}
+
+vec2 cmul(vec2 a, vec2 b) {
+    return vec2(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+vec2 cdiv(vec2 a, vec2 b) {
+    return vec2(a.x * b.x + a.y * b.y, a.y * b.x - a.x * b.y) / dot(b, b);
+}
+
+// orbit points this close count as the same, exact comparison almost never
+// triggers in float
+const float PERIOD_EPSILON = 1e-6;
+
+// everything the colorize pass needs to know about a point
+struct Sample {
+    float iterations;
+    // continuous iteration count, max_iters for points that never escape
+    float smooth_iters;
+    // distance estimate to the boundary. from outside with dz/dc for points that
+    // escape, from inside with the cycle derivatives for points caught in an
+    // attracting cycle, 0 if neither
+    float de;
+    // last z, a point of the attracting cycle when one was found
+    vec2 z;
+    // cycle length the periodicity check found, 0 if it found none
+    float period;
+    // |multiplier| of that cycle, 0 at the centers of the components and 1 on
+    // their edges
+    float multiplier;
+};
+
+// the iteration itself. user formulas replace everything from here to formula end,
+// see `formula::Expression::specialize`
+// formula begin
+
+// one step of z and of dz/dc along with it
+void step(inout vec2 z, inout vec2 dz, vec2 c) {
+    // dz' = 2 z dz + 1
+    dz = 2.0 * cmul(z, dz) + vec2(1.0, 0.0);
+    z = cmul(z, z) + c;
+}
+
+// derivatives over one trip around the cycle through z
+void cycle(inout Sample s, vec2 c, uint period) {
+    vec2 z = s.z;
+    vec2 dz = vec2(1.0, 0.0);
+    vec2 dc = vec2(0.0);
+    vec2 dzdz = vec2(0.0);
+    vec2 dcdz = vec2(0.0);
+
+    for (uint i = 0; i < period; i += 1) {
+        dcdz = 2.0 * (cmul(z, dcdz) + cmul(dz, dc));
+        dzdz = 2.0 * (cmul(dz, dz) + cmul(z, dzdz));
+        dc = 2.0 * cmul(z, dc) + vec2(1.0, 0.0);
+        dz = 2.0 * cmul(z, dz);
+        z = cmul(z, z) + c;
+    }
+
+    s.multiplier = length(dz);
+    s.de = (1.0 - dot(dz, dz)) / length(dcdz + cdiv(cmul(dzdz, dc), vec2(1.0, 0.0) - dz));
+}
+
+// formula end
+
+// where an orbit stopped, enough to carry on iterating it under a higher limit
+struct OrbitState {
+    vec2 c;
+    vec2 z;
+    vec2 dz;
+    // periodicity check, see `iterate_from`
+    vec2 old;
+    uint iterations;
+    uint steps;
+    uint interval;
+    // escaped or caught in a cycle, a higher limit changes nothing
+    uint done;
+};
+
+OrbitState start(vec2 c) {
+    // z starts at c, so dz/dc starts at 1
+    return OrbitState(c, c, vec2(1.0, 0.0), vec2(0.0), 0, 0, 1, 0);
+}
+
+Sample iterate_from(inout OrbitState o) {
+    float max_iters = float(push_constants.max_iters);
+    Sample s = Sample(max_iters, max_iters, 0.0, o.z, 0.0, 0.0);
+
+    // brent style periodicity checking, z is compared against a saved point
+    // that gets replaced less and less often so long cycles are caught too
+    for (; o.iterations < push_constants.max_iters; o.iterations += 1) {
+        step(o.z, o.dz, o.c);
+
+        if (length(o.z) > 4.0) {
+            float r = length(o.z);
+
+            s.iterations = float(o.iterations);
+            s.smooth_iters = float(o.iterations) + 1.0 - log2(log(r) / log(4.0));
+            s.de = 0.5 * r * log(r) / length(o.dz);
+            s.z = o.z;
+            o.done = 1;
+
+            return s;
+        }
+
+        o.steps += 1;
+
+        if (distance(o.z, o.old) < PERIOD_EPSILON) {
+            s.z = o.z;
+            s.period = float(o.steps);
+            cycle(s, o.c, o.steps);
+            o.done = 1;
+
+            return s;
+        }
+
+        if (o.steps == o.interval) {
+            o.old = o.z;
+            o.steps = 0;
+            o.interval *= 2;
+        }
+    }
+
+    s.z = o.z;
+
+    return s;
+}
+
+Sample iterate(vec2 c) {
+    OrbitState o = start(c);
+
+    return iterate_from(o);
+}
+
+// sample of a pixel, its orbit carried on from state. supersampling takes 4 more
+// where the distance estimate says the boundary passes through it, only the
+// smooth count is averaged over them
+Sample sample_pixel(vec2 pixel, vec2 dims, inout OrbitState state) {
+    Sample s = iterate_from(state);
+
+    if (push_constants.supersample == 0 || s.iterations >= float(push_constants.max_iters) || s.de > pixel_size(dims)) {
+        return s;
+    }
+
+    float sum = s.smooth_iters;
+
+    for (uint i = 0; i < 4; i += 1) {
+        vec2 offset = vec2(i % 2 == 0 ? -0.25 : 0.25, i < 2 ? -0.25 : 0.25);
+
+        sum += iterate(to_complex(pixel + offset, dims)).smooth_iters;
+    }
+
+    s.smooth_iters = sum / 5.0;
+
+    return s;
+}
+
+Sample sample_pixel(vec2 pixel, vec2 dims) {
+    OrbitState state = start(to_complex(pixel, dims));
+
+    return sample_pixel(pixel, dims, state);
+}
+
+// exact goes in the alpha of the samples image, see `TargetSets`
+void store(ivec2 texel, Sample s, float exact) {
+    imageStore(samples, texel, vec4(s.iterations, s.smooth_iters, s.de, exact));
+    imageStore(orbits, texel, vec4(s.z, s.period, s.multiplier));
+}
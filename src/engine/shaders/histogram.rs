@@ -0,0 +1,79 @@
+// cdf of the iteration counts for the `Histogram` coloring, built from the samples image
+// and read by `mandelbrot::colorize`
+
+pub mod count {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+            layout(binding = 0, set = 0, rgba32f) uniform readonly image2D samples;
+
+            layout(binding = 1, set = 0) buffer Bins {
+                uint bins[];
+            };
+
+            layout(push_constant) uniform PushConstants {
+                uint max_iters;
+            } push_constants;
+
+            void main() {
+                ivec2 size = imageSize(samples);
+                ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+
+                if (texel.x >= size.x || texel.y >= size.y) {
+                    return;
+                }
+
+                // stand ins count too, they cover their whole block until it's refined
+                float v = imageLoad(samples, texel).r / float(push_constants.max_iters);
+
+                if (v >= 1.0) {
+                    return;
+                }
+
+                uint bin = min(uint(v * bins.length()), bins.length() - 1);
+
+                atomicAdd(bins[bin], 1);
+            }
+        ",
+    }
+}
+
+pub mod scan {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            // a few thousand bins, one invocation walks them faster than a parallel
+            // scan would take to set up
+            layout(local_size_x = 1, local_size_y = 1, local_size_z = 1) in;
+
+            layout(binding = 0, set = 0) readonly buffer Bins {
+                uint bins[];
+            };
+
+            // fraction of escaped pixels at or below each bin
+            layout(binding = 1, set = 0) writeonly buffer Cdf {
+                float cdf[];
+            };
+
+            void main() {
+                uint total = 0;
+
+                for (uint i = 0; i < bins.length(); i += 1) {
+                    total += bins[i];
+                }
+
+                uint sum = 0;
+
+                for (uint i = 0; i < bins.length(); i += 1) {
+                    sum += bins[i];
+                    cdf[i] = float(sum) / float(max(total, 1));
+                }
+            }
+        ",
+    }
+}
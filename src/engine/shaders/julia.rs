@@ -0,0 +1,48 @@
+pub mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+            layout(binding = 0, set = 0, rgba8) uniform writeonly image2D image;
+
+            // c is fixed for the whole image, z0 comes from the pixel
+            layout(push_constant) uniform PushConstants {
+                vec2 c;
+                float scale;
+                uint max_iters;
+            } push_constants;
+
+            void main() {
+                ivec2 dims = imageSize(image);
+
+                if (gl_GlobalInvocationID.x >= dims.x || gl_GlobalInvocationID.y >= dims.y) {
+                    return;
+                }
+
+                vec2 norm = vec2(gl_GlobalInvocationID.xy) / vec2(dims);
+                vec2 z = (norm * 4.0 - 2.0) / push_constants.scale;
+                vec2 c = push_constants.c;
+
+                uint iterations;
+
+                for (iterations = 0; iterations < push_constants.max_iters; iterations += 1) {
+                    z = vec2(
+                        z.x * z.x - z.y * z.y + c.x,
+                        2.0 * z.x * z.y + c.y
+                    );
+
+                    if (length(z) > 4.0) {
+                        break;
+                    }
+                }
+
+                float i = float(iterations) / push_constants.max_iters;
+                vec4 pixel = vec4(vec3(i), 1.0);
+
+                imageStore(image, ivec2(gl_GlobalInvocationID.xy), pixel);
+            }
+        ",
+    }
+}
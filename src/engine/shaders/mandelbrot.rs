@@ -1,120 +1,216 @@
+// the kernels that can be swapped at runtime keep their source in glsl/, see
+// `renderer::shader_watch`
+pub const CS_SOURCE: &str = include_str!("glsl/mandelbrot.comp");
+pub const SUBDIVIDE_SOURCE: &str = include_str!("glsl/subdivide.comp");
+pub const COLORIZE_SOURCE: &str = include_str!("glsl/colorize.comp");
+pub const VS_SOURCE: &str = include_str!("glsl/mandelbrot.vert");
+pub const FS_SOURCE: &str = include_str!("glsl/mandelbrot.frag");
+
+// sampling both iteration kernels include, what user formulas are spliced into. see
+// `Formula::specialize`
+pub const SAMPLE_SOURCE: &str = include_str!("glsl/sample.glsl");
+
 pub mod cs {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/engine/shaders/glsl/mandelbrot.comp",
+    }
+}
+
+pub mod reproject {
     vulkano_shaders::shader!{
         ty: "compute",
         src: r"
             #version 460
 
             layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
-            layout(binding = 0, set = 0, rgba8) uniform writeonly image2D image;
+            layout(binding = 0, set = 0, rgba32f) uniform readonly image2D previous_samples;
+            layout(binding = 1, set = 0, rgba16f) uniform readonly image2D previous_orbits;
+            layout(binding = 2, set = 0, rgba32f) uniform writeonly image2D samples;
+            layout(binding = 3, set = 0, rgba16f) uniform writeonly image2D orbits;
 
+            // the view the previous frame was rendered with and the new one
             layout(push_constant) uniform PushConstants {
                 float scale;
                 vec2 translation;
+                float rotation;
+                float previous_scale;
+                vec2 previous_translation;
+                float previous_rotation;
+                // zero when the iteration limit or supersampling changed, nothing stays exact then
+                uint same_sampling;
+            } push_constants;
+
+            // how far off a whole previous pixel a sample may land and still count as exact
+            const float EXACT_EPSILON = 0.01;
+
+            // same mapping as the `cs` kernel
+            vec2 to_complex(vec2 pixel, vec2 dims, float scale, vec2 translation, float rotation) {
+                float ar = dims.x / dims.y;
+                vec2 norm = pixel / dims;
+
+                vec2 p = vec2(ar * (norm.x * 4.0 / scale) - (2.0 / scale), (norm.y * 4.0 / scale) - (2.0 / scale));
+                vec2 mid = vec2(ar * (2.0 / scale) - (2.0 / scale), 0.0);
+
+                float s = sin(rotation);
+                float co = cos(rotation);
+                vec2 d = p - mid;
+
+                return mid + vec2(co * d.x - s * d.y, s * d.x + co * d.y) + translation;
+            }
+
+            vec2 to_pixel(vec2 point, vec2 dims, float scale, vec2 translation, float rotation) {
+                float ar = dims.x / dims.y;
+                vec2 mid = vec2(ar * (2.0 / scale) - (2.0 / scale), 0.0);
+
+                float s = sin(-rotation);
+                float co = cos(-rotation);
+                vec2 d = point - translation - mid;
+                vec2 p = mid + vec2(co * d.x - s * d.y, s * d.x + co * d.y);
+
+                vec2 norm = vec2((p.x + 2.0 / scale) * scale / (4.0 * ar), (p.y + 2.0 / scale) * scale / 4.0);
+
+                return norm * dims;
+            }
+
+            void main() {
+                ivec2 size = imageSize(samples);
+                ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
+
+                if (texel.x >= size.x || texel.y >= size.y) {
+                    return;
+                }
+
+                vec2 dims = vec2(size);
+                vec2 pixel = vec2(gl_GlobalInvocationID.xy);
+
+                vec2 c = to_complex(pixel, dims, push_constants.scale, push_constants.translation, push_constants.rotation);
+                vec2 source = to_pixel(c, dims, push_constants.previous_scale, push_constants.previous_translation, push_constants.previous_rotation);
+
+                ivec2 nearest = ivec2(floor(source + 0.5));
+
+                // newly exposed, nothing to reuse
+                if (nearest.x < 0 || nearest.y < 0 || nearest.x >= size.x || nearest.y >= size.y) {
+                    imageStore(samples, texel, vec4(0.0));
+                    imageStore(orbits, texel, vec4(0.0));
+                    return;
+                }
+
+                vec4 previous = imageLoad(previous_samples, nearest);
+
+                // only a sample taken at exactly this point can be kept as is
+                bool exact = push_constants.same_sampling != 0
+                    && previous.a > 0.5
+                    && all(lessThan(abs(source - vec2(nearest)), vec2(EXACT_EPSILON)));
+
+                imageStore(samples, texel, vec4(previous.rgb, exact ? 1.0 : 0.0));
+                imageStore(orbits, texel, imageLoad(previous_orbits, nearest));
+            }
+        ",
+    }
+}
+
+pub mod subdivide {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/engine/shaders/glsl/subdivide.comp",
+    }
+}
+
+pub mod stats {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        src: r"
+            #version 460
+
+            layout(local_size_x = 8, local_size_y = 8, local_size_z = 1) in;
+            layout(binding = 0, set = 0, rgba32f) uniform readonly image2D samples;
+
+            // counts over the exact pixels of a finished view
+            layout(binding = 1, set = 0) buffer Stats {
+                uint exact;
+                uint escaped;
+                // escaped in the upper half of the iteration range
+                uint late;
+                uint limit;
+                // hit the limit right next to an escaped pixel
+                uint boundary;
+                // highest iteration count of an escaped pixel, the bits of the float. for
+                // positive floats those order the same way, so atomicMax works on them
+                uint highest;
+            } stats;
+
+            layout(push_constant) uniform PushConstants {
                 uint max_iters;
-    
             } push_constants;
 
-            // https://github.com/hughsk/glsl-hsv2rgb
-            vec3 hsv2rgb(vec3 c) {
-                vec4 K = vec4(1.0, 2.0 / 3.0, 1.0 / 3.0, 3.0);
-                vec3 p = abs(fract(c.xxx + K.xyz) * 6.0 - K.www);
-                return c.z * mix(K.xxx, clamp(p - K.xxx, 0.0, 1.0), c.y);
+            const float LATE = 0.5;
+
+            bool escaped(ivec2 texel, ivec2 size) {
+                if (texel.x < 0 || texel.y < 0 || texel.x >= size.x || texel.y >= size.y) {
+                    return false;
+                }
+
+                return imageLoad(samples, texel).r < float(push_constants.max_iters);
             }
 
             void main() {
-                vec2 dims = vec2(imageSize(image));
+                ivec2 size = imageSize(samples);
+                ivec2 texel = ivec2(gl_GlobalInvocationID.xy);
 
-                if (gl_GlobalInvocationID.x > dims.x || gl_GlobalInvocationID.x < dims.x) {
+                if (texel.x >= size.x || texel.y >= size.y) {
                     return;
                 }
 
-                if (gl_GlobalInvocationID.y > dims.y || gl_GlobalInvocationID.y < dims.y) {
+                vec4 value = imageLoad(samples, texel);
+
+                if (value.a < 0.5) {
                     return;
                 }
-                
-                float ar = dims.x / dims.y;
-                float x_norm = (gl_GlobalInvocationID.x / dims.x);
-                float y_norm = (gl_GlobalInvocationID.y / dims.y);
-    
-                float x0 = ar * (x_norm * 4.0 / push_constants.scale) - (2.0 / push_constants.scale) + push_constants.translation.x;
-                float y0 = (y_norm * 4.0 / push_constants.scale) - (2.0 / push_constants.scale) + push_constants.translation.y;
-
-                uint iterations;
-
-                vec2 c = vec2(x0, y0);
-                vec2 z = c;
-
-                vec2 old = vec2(0.0, 0.0);
-                uint period = 0;
-
-                for (iterations = 0; iterations < push_constants.max_iters; iterations += 1) {
-                    z = vec2(
-                        z.x * z.x - z.y * z.y + c.x,
-                        z.y * z.x + z.x * z.y + c.y
-                    );
-            
-                    if (length(z) > 4.0) {
-                        break;
-                    }
-                    
-                    // periodicity checking
-                    if (z == old) {
-                        iterations = push_constants.max_iters;
-                        break;
-                    }
 
-                    period += 1;
-                    if (period > 20) {
-                        period = 0;
-                        old = z;
+                atomicAdd(stats.exact, 1);
+
+                float v = value.r / float(push_constants.max_iters);
+
+                if (v < 1.0) {
+                    atomicAdd(stats.escaped, 1);
+                    atomicMax(stats.highest, floatBitsToUint(value.r));
+
+                    if (v > LATE) {
+                        atomicAdd(stats.late, 1);
                     }
-                }
 
-                float i = float(iterations) / push_constants.max_iters;
-                vec4 pixel = vec4(vec3(i), 0.1);
+                    return;
+                }
 
+                atomicAdd(stats.limit, 1);
 
-                imageStore(image, ivec2(gl_GlobalInvocationID.xy), pixel);
+                if (escaped(texel + ivec2(1, 0), size) || escaped(texel - ivec2(1, 0), size)
+                        || escaped(texel + ivec2(0, 1), size) || escaped(texel - ivec2(0, 1), size)) {
+                    atomicAdd(stats.boundary, 1);
+                }
             }
         ",
     }
 }
 
+pub mod colorize {
+    vulkano_shaders::shader!{
+        ty: "compute",
+        path: "src/engine/shaders/glsl/colorize.comp",
+    }
+}
+
 pub mod vs {
     vulkano_shaders::shader! {
         ty: "vertex",
-        src: "
-        #version 460
-
-        layout(location = 0) in vec2 position;
-        layout(location = 1) in vec2 tex_coords;
-
-        layout(location = 0) out vec2 f_tex_coords;
-
-        void main() {
-            gl_Position = vec4(position.xy, 0.0, 1.0);
-            f_tex_coords = tex_coords;;
-        }
-        "
+        path: "src/engine/shaders/glsl/mandelbrot.vert",
     }
 }
 
 pub mod fs {
     vulkano_shaders::shader! {
         ty: "fragment",
-        src: "
-        #version 460
-
-        layout(location = 0) in vec2 tex_coords;
-
-        layout(location = 0) out vec4 f_color;
-
-        layout(set = 0, binding = 0) uniform sampler2D tex;
-
-        void main() {
-            f_color = texture(tex, tex_coords);
-        }
-
-        "
+        path: "src/engine/shaders/glsl/mandelbrot.frag",
     }
 }
\ No newline at end of file
@@ -1 +1,5 @@
-pub mod mandelbrot;
\ No newline at end of file
+pub mod mandelbrot;
+pub mod overlay;
+pub mod julia;
+pub mod histogram;
+pub mod cache;
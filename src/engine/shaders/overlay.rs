@@ -0,0 +1,44 @@
+pub mod vs {
+    vulkano_shaders::shader! {
+        ty: "vertex",
+        src: "
+        #version 460
+
+        layout(location = 0) in vec2 position;
+        layout(location = 1) in vec2 tex_coords;
+        layout(location = 2) in vec4 color;
+
+        layout(location = 0) out vec2 f_tex_coords;
+        layout(location = 1) out vec4 f_color;
+
+        void main() {
+            gl_Position = vec4(position.xy, 0.0, 1.0);
+            f_tex_coords = tex_coords;
+            f_color = color;
+        }
+        "
+    }
+}
+
+pub mod fs {
+    vulkano_shaders::shader! {
+        ty: "fragment",
+        src: "
+        #version 460
+
+        layout(location = 0) in vec2 tex_coords;
+        layout(location = 1) in vec4 color;
+
+        layout(location = 0) out vec4 f_color;
+
+        // single channel glyph coverage, the solid slot is used for plain shapes
+        layout(set = 0, binding = 0) uniform sampler2D atlas;
+
+        void main() {
+            float coverage = texture(atlas, tex_coords).r;
+
+            f_color = vec4(color.rgb, color.a * coverage);
+        }
+        "
+    }
+}
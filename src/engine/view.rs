@@ -0,0 +1,102 @@
+use super::formula::{Expression, FormulaError};
+use super::renderer::coloring::{Coloring, Interior};
+use super::renderer::render::{RenderCamera, BASE_ITERS};
+
+// the iteration a view is drawn with
+#[derive(Clone, Debug, PartialEq)]
+pub enum Formula {
+    // z -> z^2 + c
+    Mandelbrot,
+    // typed in by the user, see `Expression`
+    Custom(Expression)
+}
+
+impl Formula {
+    pub fn parse(source: &str) -> Result<Formula, FormulaError> {
+        Expression::parse(source).map(Formula::Custom)
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Formula::Mandelbrot => "z^2 + c",
+            Formula::Custom(expression) => &expression.source,
+        }
+    }
+
+    // one step of the iteration on the cpu
+    pub fn step(&self, z: [f64; 2], c: [f64; 2]) -> [f64; 2] {
+        match self {
+            Formula::Mandelbrot => [z[0] * z[0] - z[1] * z[1] + c[0], 2.0 * z[0] * z[1] + c[1]],
+            Formula::Custom(expression) => expression.step(z, c),
+        }
+    }
+
+    // glsl of the sampling the `cs` and `subdivide` kernels include, iterating this formula
+    pub fn specialize(&self, sample: &str) -> Result<String, String> {
+        match self {
+            Formula::Mandelbrot => Ok(sample.to_string()),
+            Formula::Custom(expression) => expression.specialize(sample),
+        }
+    }
+}
+
+// what to draw, independent of the window or image it ends up in. this is the part of the
+// renderer's state other tools set and read back, see `Engine::set_view` and `Offscreen`
+#[derive(Clone, Debug, PartialEq)]
+pub struct View {
+    // complex coordinate of the middle of the image
+    pub center: [f64; 2],
+    // 1 fits [-2, 2] into the image height
+    pub zoom: f64,
+    // radians, the image turns around its middle
+    pub rotation: f64,
+    pub max_iters: u32,
+    pub formula: Formula,
+    pub coloring: Coloring,
+    pub interior: Interior
+}
+
+impl View {
+    pub fn new() -> View {
+        View {
+            center: [0.0, 0.0],
+            zoom: 1.0,
+            rotation: 0.0,
+            max_iters: BASE_ITERS,
+            formula: Formula::Mandelbrot,
+            coloring: Coloring::Iterations,
+            interior: Interior::Flat
+        }
+    }
+
+    // the kernels' camera for an image of this size. their translation is the middle of
+    // a square image, wider ones reach further to the right
+    pub(crate) fn camera(&self, dims: [f32; 2]) -> RenderCamera {
+        let zoom = self.zoom as f32;
+        let mid = (dims[0] / dims[1]) * (2.0 / zoom) - 2.0 / zoom;
+
+        RenderCamera {
+            translation: [self.center[0] as f32 - mid, self.center[1] as f32],
+            zoom,
+            rotation: self.rotation as f32,
+            max_iters: self.max_iters,
+            coloring: self.coloring,
+            interior: self.interior
+        }
+    }
+
+    // inverse of `camera`, the formula isn't part of the camera
+    pub(crate) fn from_camera(camera: &RenderCamera, formula: &Formula, dims: [f32; 2]) -> View {
+        let center = camera.to_complex([dims[0] / 2.0, dims[1] / 2.0], dims);
+
+        View {
+            center: [center[0] as f64, center[1] as f64],
+            zoom: camera.zoom as f64,
+            rotation: camera.rotation as f64,
+            max_iters: camera.max_iters,
+            formula: formula.clone(),
+            coloring: camera.coloring,
+            interior: camera.interior
+        }
+    }
+}
@@ -0,0 +1,11 @@
+// mandelbrowser as a library, for tools that embed the renderer. a `View` says what to
+// draw, `Engine` draws it into a window and `Offscreen` into an image on the gpu or the
+// cpu. the mandelbrowser binary is the window and its key bindings on top of this
+pub mod engine;
+
+pub use engine::device::DeviceChoice;
+pub use engine::formula::{Expression, FormulaError};
+pub use engine::engine::Engine;
+pub use engine::offscreen::{Backend, Offscreen};
+pub use engine::view::{Formula, View};
+pub use engine::{Coloring, Interior};
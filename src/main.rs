@@ -1,56 +1,206 @@
-#![allow(unused_imports, dead_code)]
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant};
 
-use engine::engine::Engine;
-use winit::event::{Event, WindowEvent, KeyboardInput, VirtualKeyCode, MouseScrollDelta};
+use mandelbrowser::engine::device::{self, DeviceChoice};
+use mandelbrowser::engine::engine::Engine;
+use mandelbrowser::engine::view::Formula;
+use winit::event::{Event, WindowEvent, KeyboardInput, VirtualKeyCode, MouseScrollDelta, ElementState, MouseButton, ModifiersState};
 
-mod engine;
+// how often watched shader files are checked for changes
+const SHADER_POLL: Duration = Duration::from_millis(250);
 
 fn main() {
-    println!("hello world");
+    let mut device = None;
+    let mut shader_dir = None;
+    let mut cache_dir = None;
+    let mut formula = None;
+    let mut args = env::args().skip(1);
 
-    
-    let (mut engine, event_loop) = Engine::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--list-devices" => {
+                device::list_devices();
+                return;
+            }
 
-    event_loop.run(move |event, _, control_flow| {
-        control_flow.set_wait();
+            // by index or name, see `--list-devices`
+            "--device" => {
+                let choice = match args.next() {
+                    Some(value) => DeviceChoice::parse(&value),
+                    None => {
+                        eprintln!("--device needs an index or a name");
+                        process::exit(1);
+                    }
+                };
 
-        match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    control_flow.set_exit();
-                    println!("goodbye world");
+                device = match device::resolve(&choice) {
+                    Some(id) => Some(id),
+                    None => {
+                        eprintln!("no device matches {:?}, see --list-devices", choice);
+                        process::exit(1);
+                    }
+                };
+            }
+
+            // glsl to compile at runtime and reload on change, a copy of src/engine/shaders/glsl
+            "--shaders" => match args.next() {
+                Some(dir) => shader_dir = Some(PathBuf::from(dir)),
+                None => {
+                    eprintln!("--shaders needs a directory");
+                    process::exit(1);
                 }
+            }
 
-                WindowEvent::Resized(_) => {
-                    engine.resize();
+            // where the tile cache (K) keeps tiles that don't fit in memory, kept across runs
+            "--cache-dir" => match args.next() {
+                Some(dir) => cache_dir = Some(PathBuf::from(dir)),
+                None => {
+                    eprintln!("--cache-dir needs a directory");
+                    process::exit(1);
                 }
-                WindowEvent::KeyboardInput { input, .. } => {
-                    handle_input(&mut engine, input);
+            }
+
+            // e.g. "z^3 + c*sin(z)", see `Expression` for what it understands
+            "--formula" => match args.next() {
+                Some(source) => formula = Some(parse_formula(&source)),
+                None => {
+                    eprintln!("--formula needs an expression");
+                    process::exit(1);
                 }
+            }
+
+            // the same from a file, lines starting with # are comments
+            "--formula-file" => match args.next() {
+                Some(path) => {
+                    let contents = fs::read_to_string(&path).unwrap_or_else(|e| {
+                        eprintln!("can't read {}: {}", path, e);
+                        process::exit(1);
+                    });
 
-                WindowEvent::MouseWheel { delta, ..} => {
-                    let delta = match delta {
-                        MouseScrollDelta::LineDelta(_x, y) => y,
-                        MouseScrollDelta::PixelDelta(pos) => pos.y as f32,
-                    };
+                    let source: Vec<&str> = contents
+                        .lines()
+                        .map(|line| line.trim())
+                        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                        .collect();
 
-                    engine.zoom(delta);
+                    formula = Some(parse_formula(&source.join(" ")));
                 }
+                None => {
+                    eprintln!("--formula-file needs a path");
+                    process::exit(1);
+                }
+            }
 
-                _ => ()
+            _ => {
+                eprintln!("unknown argument {}", arg);
+                process::exit(1);
             }
-            Event::MainEventsCleared => {
+        }
+    }
 
+    let (mut engine, event_loop) = Engine::new(device, cache_dir);
+
+    if let Some(dir) = shader_dir {
+        engine.watch_shaders(dir);
+    }
+
+    if let Some(formula) = formula {
+        if let Err(e) = engine.set_formula(formula) {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+    let mut modifiers = ModifiersState::empty();
+    // when the watched shader files are checked next
+    let mut next_poll = Instant::now();
+
+    event_loop.run(move |event, _, control_flow| {
+        match event {
+            // the engine notices what changes the frame by itself, see `Engine::render`
+            Event::WindowEvent { event, .. } => {
+                match event {
+                    WindowEvent::CloseRequested => {
+                        control_flow.set_exit();
+                    }
+
+                    // a zero size means minimized, the swapchain gets rebuilt once it's restored
+                    WindowEvent::Resized(size) => {
+                        if size.width > 0 && size.height > 0 {
+                            engine.resize();
+                        }
+                    }
+                    WindowEvent::ScaleFactorChanged { .. } => {
+                        engine.resize();
+                    }
+                    WindowEvent::ModifiersChanged(state) => {
+                        modifiers = state;
+                    }
+                    WindowEvent::KeyboardInput { input, .. } => {
+                        handle_input(&mut engine, input, modifiers);
+                    }
+
+                    WindowEvent::MouseWheel { delta, ..} => {
+                        let delta = match delta {
+                            MouseScrollDelta::LineDelta(_x, y) => y,
+                            MouseScrollDelta::PixelDelta(pos) => pos.y as f32,
+                        };
+
+                        // ctrl turns the view instead
+                        if modifiers.ctrl() {
+                            engine.rotate(delta);
+                        } else {
+                            engine.zoom_at_cursor(delta);
+                        }
+                    }
+
+                    WindowEvent::CursorMoved { position, .. } => {
+                        engine.cursor_moved([position.x as f32, position.y as f32]);
+                    }
+
+                    WindowEvent::CursorLeft { .. } => {
+                        engine.cursor_left();
+                    }
+
+                    WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => match button {
+                        MouseButton::Left => engine.select_point(),
+                        MouseButton::Right => engine.clear_orbit(),
+                        _ => ()
+                    }
+
+                    _ => ()
+                }
             }
+            // the os wants the window contents back, e.g. after it was uncovered
+            Event::RedrawRequested(_) => {
+                engine.request_redraw();
+            }
+            Event::MainEventsCleared => {
+                if engine.watches_shaders() && Instant::now() >= next_poll {
+                    engine.reload_shaders();
+                    next_poll = Instant::now() + SHADER_POLL;
+                }
+            }
+            // last event of every iteration, what it leaves in control_flow is what the loop
+            // waits with. watched shaders wake it up for the next poll even when nothing
+            // else happens
             Event::RedrawEventsCleared => {
                 engine.render();
+
+                if engine.watches_shaders() {
+                    control_flow.set_wait_until(next_poll);
+                } else {
+                    control_flow.set_wait();
+                }
             }
 
             _ => (),
         }
     });
 
-    fn handle_input(engine: &mut Engine, input: KeyboardInput) {
+    fn handle_input(engine: &mut Engine, input: KeyboardInput, modifiers: ModifiersState) {
         let keycode = input.virtual_keycode;
 
         match keycode {
@@ -89,6 +239,61 @@ fn main() {
                         engine.reset_camera();
                     }
 
+                    VirtualKeyCode::Z => {
+                        engine.rotate(1.0);
+                    }
+
+                    VirtualKeyCode::X => {
+                        engine.rotate(-1.0);
+                    }
+
+                    // toggles only fire on press, not on release
+                    VirtualKeyCode::H if input.state == ElementState::Pressed => {
+                        engine.toggle_hud();
+                    }
+
+                    VirtualKeyCode::J if input.state == ElementState::Pressed => {
+                        engine.toggle_julia();
+                    }
+
+                    VirtualKeyCode::M if input.state == ElementState::Pressed => {
+                        engine.toggle_minimap();
+                    }
+
+                    VirtualKeyCode::I if input.state == ElementState::Pressed => {
+                        engine.toggle_auto_iters();
+                    }
+
+                    VirtualKeyCode::B if input.state == ElementState::Pressed => {
+                        engine.toggle_subdivide();
+                    }
+
+                    VirtualKeyCode::K if input.state == ElementState::Pressed => {
+                        engine.toggle_cache();
+                    }
+
+                    VirtualKeyCode::C if input.state == ElementState::Pressed => {
+                        engine.cycle_coloring();
+                    }
+
+                    VirtualKeyCode::V if input.state == ElementState::Pressed => {
+                        engine.cycle_interior();
+                    }
+
+                    // shift for exclusive fullscreen instead of borderless
+                    VirtualKeyCode::F11 if input.state == ElementState::Pressed => {
+                        engine.toggle_fullscreen(modifiers.shift());
+                    }
+
+                    VirtualKeyCode::Escape if input.state == ElementState::Pressed => {
+                        engine.exit_fullscreen();
+                    }
+
+                    // shift for a hi res render instead of the window contents
+                    VirtualKeyCode::P if input.state == ElementState::Pressed => {
+                        engine.screenshot(modifiers.shift());
+                    }
+
 
                     _ => ()
                 }
@@ -101,4 +306,11 @@ fn main() {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+fn parse_formula(source: &str) -> Formula {
+    Formula::parse(source).unwrap_or_else(|e| {
+        eprintln!("bad formula:\n{}", e);
+        process::exit(1);
+    })
+}